@@ -0,0 +1,217 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::report::{self, Row};
+use crate::{OutputFormat, RawInfo, Settings};
+
+const GROUP_WRITE: u32 = 0o020;
+const OTHER_WRITE: u32 = 0o002;
+const GROUP_OTHER_ACCESS: u32 = 0o077;
+
+/// Number of entries per permission mode, plus the entries whose permissions look wrong.
+#[derive(Serialize)]
+struct PermSummary {
+    modes: Vec<ModeCount>,
+    anomalies: Vec<Anomaly>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ModeCount {
+    mode: String,
+    count: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Anomaly {
+    path: String,
+    message: String,
+}
+
+/// Prints how many entries have each permission mode, most common first, followed by warnings
+/// about world-writable entries and writable entries in directories closed to everyone else.
+pub fn print(raw_infos: &[RawInfo], settings: &Settings) -> i32 {
+    let summary = summarize(raw_infos);
+
+    match settings.output {
+        OutputFormat::Table => print_table(&summary, settings),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summary).unwrap()),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&summary).unwrap()),
+        OutputFormat::Csv => print_delimited(&summary, ','),
+        OutputFormat::Tsv => print_delimited(&summary, '\t'),
+    }
+
+    report::exit_code(settings, summary.modes.is_empty())
+}
+
+/// A line of a CSV/TSV summary: a mode with its count, or an anomaly with its path and message.
+#[derive(Debug, PartialEq, Serialize)]
+struct Record {
+    kind: &'static str,
+    mode: String,
+    count: String,
+    path: String,
+    message: String,
+}
+
+impl Row for Record {
+    const FIELDS: &'static [&'static str] = &["kind", "mode", "count", "path", "message"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.kind.to_string(),
+            self.mode.clone(),
+            self.count.clone(),
+            self.path.clone(),
+            self.message.clone(),
+        ]
+    }
+}
+
+fn summarize(raw_infos: &[RawInfo]) -> PermSummary {
+    // Modes of the directories at hand, so parents are only read from disk when missing
+    let dir_modes: HashMap<&Path, u32> = raw_infos
+        .iter()
+        .filter(|r| r.is_directory)
+        .map(|r| (r.path.as_path(), r.permissions()))
+        .collect();
+
+    let mut counts: BTreeMap<u32, u64> = BTreeMap::new();
+    let mut anomalies = Vec::new();
+    for raw_info in raw_infos.iter().filter(|r| !r.is_main_dir) {
+        *counts.entry(raw_info.permissions()).or_default() += 1;
+
+        // Links always have mode 777, what matters is their target
+        if raw_info.is_symlink {
+            continue;
+        }
+
+        if raw_info.permissions() & OTHER_WRITE != 0 {
+            anomalies.push(Anomaly::new(raw_info, "world-writable".to_string()));
+        }
+
+        let parent_mode = raw_info.path.parent().and_then(|parent| {
+            dir_modes.get(parent).copied().or_else(|| {
+                parent
+                    .symlink_metadata()
+                    .ok()
+                    .map(|m| m.permissions().mode() & 0o777)
+            })
+        });
+        if let Some(parent_mode) = parent_mode {
+            let writable = raw_info.permissions() & (GROUP_WRITE | OTHER_WRITE) != 0;
+            if writable && parent_mode & GROUP_OTHER_ACCESS == 0 {
+                let message = format!(
+                    "group or world-writable under a {:03o} directory",
+                    parent_mode
+                );
+                anomalies.push(Anomaly::new(raw_info, message));
+            }
+        }
+    }
+
+    let mut modes: Vec<ModeCount> = counts
+        .into_iter()
+        .map(|(mode, count)| ModeCount {
+            mode: format!("{:03o}", mode),
+            count,
+        })
+        .collect();
+    modes.sort_by_key(|m| Reverse(m.count));
+
+    PermSummary { modes, anomalies }
+}
+
+impl Anomaly {
+    fn new(raw_info: &RawInfo, message: String) -> Self {
+        Anomaly {
+            path: raw_info.path.display().to_string(),
+            message,
+        }
+    }
+}
+
+// Mode counts and anomalies share the columns, told apart by their kind
+fn print_delimited(summary: &PermSummary, separator: char) {
+    report::print_delimited(&records(summary), separator);
+}
+
+fn records(summary: &PermSummary) -> Vec<Record> {
+    let modes = summary.modes.iter().map(|mode_count| Record {
+        kind: "mode",
+        mode: mode_count.mode.clone(),
+        count: mode_count.count.to_string(),
+        path: String::new(),
+        message: String::new(),
+    });
+    let anomalies = summary.anomalies.iter().map(|anomaly| Record {
+        kind: "anomaly",
+        mode: String::new(),
+        count: String::new(),
+        path: anomaly.path.clone(),
+        message: anomaly.message.clone(),
+    });
+    modes.chain(anomalies).collect()
+}
+
+fn print_table(summary: &PermSummary, settings: &Settings) {
+    println!("{}", settings.header("MODE   COUNT"));
+    for mode_count in &summary.modes {
+        println!("{:<4} {:>7}", mode_count.mode, mode_count.count);
+    }
+
+    if !summary.anomalies.is_empty() {
+        println!();
+        for anomaly in &summary.anomalies {
+            println!("Warning: {} is {}", anomaly.path, anomaly.message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_raw_info;
+
+    #[test]
+    fn test_summarize() {
+        let raw_info = |path, permissions: u32, is_directory| {
+            let raw_info = mock_raw_info(path, 0, is_directory);
+            RawInfo {
+                mode: raw_info.mode & !0o777 | permissions,
+                ..raw_info
+            }
+        };
+        let summary = summarize(&[
+            RawInfo {
+                is_main_dir: true,
+                ..raw_info("/srv/www", 0o700, true)
+            },
+            raw_info("/srv/www/index.html", 0o644, false),
+            raw_info("/srv/www/style.css", 0o644, false),
+            raw_info("/srv/www/upload", 0o664, false),
+            raw_info("/srv/www/tmp", 0o777, true),
+        ]);
+
+        let modes: Vec<(&str, u64)> = summary
+            .modes
+            .iter()
+            .map(|m| (m.mode.as_str(), m.count))
+            .collect();
+        assert_eq!(modes, vec![("644", 2), ("664", 1), ("777", 1)]);
+
+        let paths: Vec<&str> = summary.anomalies.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/srv/www/upload", "/srv/www/tmp", "/srv/www/tmp"]
+        );
+
+        let records: Vec<Vec<String>> = records(&summary).iter().map(Record::fields).collect();
+        assert_eq!(records[0], ["mode", "644", "2", "", ""]);
+        assert_eq!(records[4], ["anomaly", "", "", "/srv/www/tmp", "world-writable"]);
+        assert_eq!(records.len(), 6);
+    }
+}
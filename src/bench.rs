@@ -0,0 +1,132 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::source::LocalFs;
+use crate::{scan_paths, write_table, DisplayArgs, Settings};
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// Directory to list (default: current directory)
+    #[arg(default_value = ".")]
+    path: String,
+
+    /// Number of timed runs, the fastest one counting (after an untimed one warming up caches)
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+/// Time taken by each phase of a listing.
+#[derive(Clone, Copy, Default)]
+struct Timings {
+    scan: Duration,
+    process: Duration,
+    format: Duration,
+}
+
+impl Timings {
+    fn total(&self) -> Duration {
+        self.scan + self.process + self.format
+    }
+}
+
+/// Times the phases of a listing of the directory, as myls does it with the given display
+/// options, against `ls -l` listing the same directory (starting it included). Output is thrown
+/// away, so the terminal doesn't count.
+pub fn run(args: &BenchArgs, settings: &Settings) -> i32 {
+    let path = PathBuf::from(&args.path);
+    if !path.is_dir() {
+        eprintln!("Error: {} is not a directory", path.display());
+        return 1;
+    }
+
+    let mut best: Option<(Timings, usize)> = None;
+    for run in 0..=args.runs {
+        let (timings, entries) = match time_listing(&args.path, settings) {
+            Some(result) => result,
+            None => return 1,
+        };
+        let faster = best.is_none_or(|(b, _)| timings.total() < b.total());
+        if run > 0 && faster {
+            best = Some((timings, entries));
+        }
+    }
+    let (timings, entries) = best.unwrap();
+
+    let mut ls = None;
+    for run in 0..=args.runs {
+        match time_ls(&args.path) {
+            Ok(elapsed) if run > 0 => ls = Some(ls.map_or(elapsed, |b: Duration| b.min(elapsed))),
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Error: could not run ls -l: {}", e);
+                break;
+            }
+        }
+    }
+
+    println!(
+        "{} ({} entries, fastest of {} runs)",
+        path.display(),
+        entries,
+        args.runs
+    );
+    println!("  scan     {:>9.1?}", timings.scan);
+    println!("  process  {:>9.1?}", timings.process);
+    println!("  format   {:>9.1?}", timings.format);
+    println!("  myls     {:>9.1?}", timings.total());
+    if let Some(ls) = ls {
+        let ratio = timings.total().as_secs_f64() / ls.as_secs_f64().max(f64::EPSILON);
+        println!(
+            "  ls -l    {:>9.1?}  (myls takes {:.2}x its time)",
+            ls, ratio
+        );
+    }
+    0
+}
+
+// Lists the directory without printing it, as a plain listing would. None if it can't be read.
+fn time_listing(path: &str, settings: &Settings) -> Option<(Timings, usize)> {
+    let start = Instant::now();
+    let mut raw_infos = scan_paths(&[PathBuf::from(path)], &settings.scan, &LocalFs)?;
+    raw_infos.retain(|raw_info| settings.keep(raw_info));
+    let scan = start.elapsed();
+    let entries = raw_infos
+        .iter()
+        .filter(|raw_info| !raw_info.is_main_dir)
+        .count();
+
+    let start = Instant::now();
+    let processed_infos = settings.process_sorted(raw_infos);
+    let process = start.elapsed();
+
+    let start = Instant::now();
+    if let Err(e) = write_table(io::sink(), processed_infos, settings) {
+        eprintln!("Error: could not format the listing: {}", e);
+        return None;
+    }
+    let format = start.elapsed();
+
+    Some((
+        Timings {
+            scan,
+            process,
+            format,
+        },
+        entries,
+    ))
+}
+
+fn time_ls(path: &str) -> io::Result<Duration> {
+    let start = Instant::now();
+    Command::new("ls")
+        .args(["-l", "--", path])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(start.elapsed())
+}
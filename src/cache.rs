@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{self, Visibility};
+
+/// What a directory directly holds, as far as its size is concerned.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct DirContents {
+    /// Total size of the files with a single link
+    pub files_size: u64,
+    /// Files with several links, as (device, inode, size), so they can be counted once
+    pub hard_links: Vec<(u64, u64, u64)>,
+    /// Names of the subdirectories
+    pub subdirs: Vec<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedDir {
+    mtime: (i64, i64),
+    contents: DirContents,
+}
+
+/// Directory contents saved under `$XDG_CACHE_HOME/myls` (or `~/.cache/myls`) between runs,
+/// keyed by path and modification time. A directory's mtime changes when entries are added,
+/// removed or renamed in it, but not when a file is rewritten in place.
+#[derive(Default)]
+pub struct SizeCache {
+    dirs: HashMap<String, CachedDir>,
+    dirty: bool,
+}
+
+impl SizeCache {
+    const FILE_NAME: &'static str = "dir-sizes.json";
+
+    /// Loads the cache file, starting empty if it's missing or unreadable.
+    pub fn load() -> Self {
+        let path = match cache_path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("No size cache loaded from {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(dirs) => {
+                info!("Loaded size cache {}", path.display());
+                SizeCache { dirs, dirty: false }
+            }
+            Err(e) => {
+                debug!("Discarding invalid size cache {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the cache file back, if anything changed.
+    pub fn save(&self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let path = cache_path().ok_or("could not determine the cache directory")?;
+        let content = serde_json::to_string(&self.dirs).map_err(|e| e.to_string())?;
+        // Cached paths may reveal what's in other users' readable directories
+        storage::write(&path, content.as_bytes(), Visibility::Private)
+    }
+
+    /// Contents of the directory, if they were cached with the same modification time.
+    pub fn get(&self, directory: &Path, mtime: (i64, i64)) -> Option<&DirContents> {
+        let cached = self.dirs.get(directory.to_str()?)?;
+        if cached.mtime == mtime {
+            Some(&cached.contents)
+        } else {
+            debug!("Size cache outdated for {}", directory.display());
+            None
+        }
+    }
+
+    /// Remembers the contents of a directory. Paths that aren't valid UTF-8 aren't cached.
+    pub fn insert(&mut self, directory: &Path, mtime: (i64, i64), contents: DirContents) {
+        if contents.subdirs.iter().any(|subdir| subdir.to_str().is_none()) {
+            return;
+        }
+        if let Some(key) = directory.to_str() {
+            self.dirs.insert(key.to_string(), CachedDir { mtime, contents });
+            self.dirty = true;
+        }
+    }
+}
+
+/// Describes the cache file, for `myls cache status`.
+pub fn status() -> Result<String, String> {
+    let path = cache_path().ok_or("could not determine the cache directory")?;
+    let size = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(format!("No size cache at {}", path.display())),
+    };
+
+    Ok(format!(
+        "Size cache at {}: {} directories, {} bytes",
+        path.display(),
+        SizeCache::load().dirs.len(),
+        size
+    ))
+}
+
+/// Deletes the cache file, returning where it was.
+pub fn clear() -> Result<PathBuf, String> {
+    let path = cache_path().ok_or("could not determine the cache directory")?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(path),
+        Err(e) => Err(format!("could not remove {}: {}", path.display(), e)),
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    storage::cache_dir().map(|dir| dir.join(SizeCache::FILE_NAME))
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+
+/// Broad kind of content of a file, guessed from its extension.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Category {
+    Image,
+    Video,
+    Audio,
+    Archive,
+    Code,
+    Document,
+}
+
+impl Category {
+    const IMAGE: &'static [&'static str] = &[
+        "png", "jpg", "jpeg", "gif", "bmp", "webp", "svg", "ico", "tif", "tiff", "heic", "avif",
+        "raw", "psd", "xcf",
+    ];
+    const VIDEO: &'static [&'static str] = &[
+        "mp4", "mkv", "avi", "mov", "webm", "flv", "wmv", "m4v", "mpg", "mpeg", "3gp", "mts",
+    ];
+    const AUDIO: &'static [&'static str] = &[
+        "mp3", "wav", "flac", "ogg", "oga", "opus", "m4a", "aac", "wma", "aiff", "mid", "midi",
+    ];
+    const ARCHIVE: &'static [&'static str] = &[
+        "zip", "tar", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "zst", "7z", "rar", "lz", "lzma",
+        "iso", "deb", "rpm", "jar", "apk", "dmg",
+    ];
+    const CODE: &'static [&'static str] = &[
+        "rs", "py", "js", "mjs", "ts", "tsx", "jsx", "c", "h", "cpp", "cc", "hpp", "java", "kt",
+        "go", "rb", "php", "cs", "swift", "scala", "sh", "bash", "zsh", "fish", "lua", "pl", "r",
+        "sql", "html", "css", "scss", "json", "yaml", "yml", "toml", "xml", "vue", "dart", "zig",
+    ];
+    const DOCUMENT: &'static [&'static str] = &[
+        "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "rst", "tex", "epub", "xls", "xlsx",
+        "ods", "csv", "ppt", "pptx", "odp",
+    ];
+
+    /// Category of a file according to its extension, if known.
+    /// ".ts" counts as TypeScript code rather than an MPEG transport stream.
+    pub fn of(path: &Path) -> Option<Category> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+
+        [
+            (Category::Image, Self::IMAGE),
+            (Category::Video, Self::VIDEO),
+            (Category::Audio, Self::AUDIO),
+            (Category::Archive, Self::ARCHIVE),
+            (Category::Code, Self::CODE),
+            (Category::Document, Self::DOCUMENT),
+        ]
+        .into_iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(category, _)| category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_of() {
+        assert_eq!(Category::of(Path::new("photo.JPG")), Some(Category::Image));
+        assert_eq!(Category::of(Path::new("backup.tar.gz")), Some(Category::Archive));
+        assert_eq!(Category::of(Path::new("src/main.rs")), Some(Category::Code));
+        assert_eq!(Category::of(Path::new("web/app.ts")), Some(Category::Code));
+        assert_eq!(Category::of(Path::new("clip.mts")), Some(Category::Video));
+        assert_eq!(Category::of(Path::new("Makefile")), None);
+    }
+}
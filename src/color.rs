@@ -0,0 +1,197 @@
+use std::env;
+
+use log::debug;
+
+/// Colors the terminal can show. Tables use the 256-color palette, which consoles limited to
+/// the basic colors (e.g. the Linux console) render as garbage or ignore.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    /// 8 foreground colors and their bright variants, and 8 backgrounds
+    Basic,
+    /// 256 colors or more
+    #[default]
+    Extended,
+}
+
+impl Palette {
+    // Terminal types known to be limited to the basic colors
+    const BASIC_TERMS: [&'static str; 8] = [
+        "linux",
+        "dumb",
+        "ansi",
+        "cons25",
+        "vt100",
+        "vt102",
+        "vt220",
+        "xterm-16color",
+    ];
+
+    /// Palette of the terminal, according to `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let palette = Self::from_env(&term, env::var("COLORTERM").ok().as_deref());
+        debug!("TERM={} uses the {:?} palette", term, palette);
+        palette
+    }
+
+    // Unknown terminals are assumed to be modern ones
+    fn from_env(term: &str, colorterm: Option<&str>) -> Self {
+        let basic = Self::BASIC_TERMS.contains(&term)
+            || term.ends_with("-8color")
+            || term.ends_with("-16color");
+        if basic && colorterm.is_none_or(str::is_empty) {
+            Palette::Basic
+        } else {
+            Palette::Extended
+        }
+    }
+
+    /// Rewrites the colors of every escape sequence in the text to ones the palette has.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Palette::Extended => text.to_string(),
+            Palette::Basic => to_basic(text),
+        }
+    }
+}
+
+/// RGB values of the basic colors, as xterm shows them.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// Replaces 256-color and truecolor parameters of SGR sequences (e.g. "\x1b[1;38;5;208m")
+// with the nearest basic color, leaving everything else untouched.
+fn to_basic(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        result.push_str(&rest[..start]);
+        let sequence = &rest[start + 2..];
+        let end = match sequence.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(end) => end,
+            None => {
+                result.push_str(&rest[start..]);
+                return result;
+            }
+        };
+        result.push_str("\x1b[");
+        if sequence.as_bytes()[end] == b'm' {
+            result.push_str(&basic_params(&sequence[..end]));
+        } else {
+            result.push_str(&sequence[..end]);
+        }
+        result.push(sequence.as_bytes()[end] as char);
+        rest = &sequence[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn basic_params(params: &str) -> String {
+    let params: Vec<&str> = params.split(';').collect();
+    let mut basic = Vec::new();
+    let mut i = 0;
+    while i < params.len() {
+        let background = match params[i] {
+            "38" => false,
+            "48" => true,
+            param => {
+                basic.push(param.to_string());
+                i += 1;
+                continue;
+            }
+        };
+        let number = |offset: usize| params.get(i + offset).and_then(|p| p.parse::<u8>().ok());
+        let (rgb, len) = match params.get(i + 1) {
+            Some(&"5") => (number(2).map(indexed_rgb), 3),
+            Some(&"2") => match (number(2), number(3), number(4)) {
+                (Some(r), Some(g), Some(b)) => (Some((r, g, b)), 5),
+                _ => (None, 5),
+            },
+            _ => (None, 1),
+        };
+        if let Some(rgb) = rgb {
+            // Bright backgrounds aren't supported everywhere, so they're left out
+            let code = if background {
+                40 + nearest(rgb, &BASIC_RGB[..8])
+            } else {
+                match nearest(rgb, &BASIC_RGB) {
+                    index @ 0..=7 => 30 + index,
+                    index => 90 + index - 8,
+                }
+            };
+            basic.push(code.to_string());
+        }
+        i += len;
+    }
+    basic.join(";")
+}
+
+// RGB value of a color of the 256-color palette.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => BASIC_RGB[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            let level = |n: u8| LEVELS[n as usize];
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn nearest((r, g, b): (u8, u8, u8), candidates: &[(u8, u8, u8)]) -> usize {
+    let distance = |&(cr, cg, cb): &(u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    (0..candidates.len())
+        .min_by_key(|&i| distance(&candidates[i]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env() {
+        assert_eq!(Palette::from_env("linux", None), Palette::Basic);
+        assert_eq!(Palette::from_env("rxvt-16color", None), Palette::Basic);
+        assert_eq!(
+            Palette::from_env("linux", Some("truecolor")),
+            Palette::Extended
+        );
+        assert_eq!(Palette::from_env("xterm-256color", None), Palette::Extended);
+        assert_eq!(Palette::from_env("", None), Palette::Extended);
+    }
+
+    #[test]
+    fn test_to_basic() {
+        assert_eq!(to_basic("\x1b[38;5;196mbig\x1b[0m"), "\x1b[91mbig\x1b[0m");
+        assert_eq!(to_basic("\x1b[1;4;38;5;208m!"), "\x1b[1;4;33m!");
+        assert_eq!(to_basic("\x1b[48;5;236m 755"), "\x1b[40m 755");
+        assert_eq!(to_basic("\x1b[38;2;0;0;250mx"), "\x1b[34mx");
+        assert_eq!(to_basic("\x1b[32mplain\x1b[2K"), "\x1b[32mplain\x1b[2K");
+    }
+}
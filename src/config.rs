@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use glob::Pattern;
+use log::{debug, info};
+use serde::Deserialize;
+use toml::Table;
+
+use crate::storage::{self, config_dir, Visibility};
+use crate::theme::ThemeName;
+use crate::{diagnostics, Column, IconKind, WritableWarning};
+
+/// User preferences read from `$XDG_CONFIG_HOME/myls/config.toml` (or `~/.config/myls/config.toml`).
+/// Every field is optional, so command line flags and built-in defaults fill the gaps.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Columns to keep on narrow terminals, from most to least important.
+    pub column_priority: Option<Vec<Column>>,
+    /// Show folder icons, as `--icons`.
+    pub icons: Option<bool>,
+    /// Kinds of entries without icons, as `--no-icons-for` (e.g. `["target"]`).
+    pub no_icons_for: Option<Vec<IconKind>>,
+    /// Glyph when icons are off, as `--icon-placeholder`.
+    pub icon_placeholder: Option<String>,
+    /// Colors by file suffix, as `--file-colors` (e.g. `".py" = "38;5;220m"`).
+    pub file_colors: Option<HashMap<String, String>>,
+    /// Shorter names shown for owners and groups in tables (e.g. `"domain users" = "du"`).
+    pub owner_aliases: Option<HashMap<String, String>>,
+    /// Color scheme, as `--theme` (e.g. `"deuteranopia"`).
+    pub theme: Option<ThemeName>,
+    /// Permissions to highlight when they allow writing, as `--writable-warning`.
+    pub writable_warning: Option<WritableWarning>,
+    /// Named sets of options, applied with `--view` (e.g. `[view.media]` with `sort = "size"`).
+    pub view: HashMap<String, Table>,
+}
+
+impl Config {
+    const FILE_NAME: &'static str = "config.toml";
+
+    /// Loads the config file, falling back to defaults if it's missing or invalid.
+    pub fn load() -> Self {
+        let path = match config_path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("No config file loaded from {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match Self::parse(&content) {
+            Ok(config) => {
+                info!("Loaded config file {}", path.display());
+                config
+            }
+            Err(e) => {
+                let message = format!("ignoring invalid config file {}: {}", path.display(), e);
+                diagnostics::warn(&message);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| e.message().to_string())
+    }
+}
+
+/// Name patterns hidden from every listing, one glob per line in the `ignore` file
+/// next to the config file. Blank lines and lines starting with # are skipped.
+pub fn load_ignore_patterns() -> Vec<Pattern> {
+    let path = match config_dir() {
+        Some(dir) => dir.join("ignore"),
+        None => return Vec::new(),
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(content) => {
+            info!("Loaded ignore file {}", path.display());
+            parse_ignore_patterns(&content)
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+fn parse_ignore_patterns(content: &str) -> Vec<Pattern> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match Pattern::new(line) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                diagnostics::warn(&format!("ignoring invalid pattern {} in ignore file: {}", line, e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Prefix identifying strings produced by `myls config export`.
+const EXPORT_PREFIX: &str = "myls1:";
+
+/// Path of the main config file.
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(Config::FILE_NAME))
+}
+
+/// Bundles the current config file into a single line that can be pasted on another machine.
+pub fn export() -> Result<String, String> {
+    let path = config_path().ok_or("could not determine the config directory")?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read config file {}: {}", path.display(), e))?;
+    Config::parse(&content).map_err(|e| format!("invalid config file {}: {}", path.display(), e))?;
+
+    Ok(format!("{}{}", EXPORT_PREFIX, URL_SAFE_NO_PAD.encode(content)))
+}
+
+/// Replaces the config file with an exported one, returning where it was written.
+/// The source may be the exported string itself, a file containing it or an http(s) URL.
+pub fn import(source: &str) -> Result<PathBuf, String> {
+    let exported = if source.starts_with(EXPORT_PREFIX) {
+        source.to_string()
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        download(source)?
+    } else {
+        fs::read_to_string(Path::new(source))
+            .map_err(|e| format!("could not read {}: {}", source, e))?
+    };
+
+    let content = decode(exported.trim())?;
+    Config::parse(&content).map_err(|e| format!("invalid config: {}", e))?;
+
+    let path = config_path().ok_or("could not determine the config directory")?;
+    storage::write(&path, content.as_bytes(), Visibility::Default)?;
+
+    Ok(path)
+}
+
+fn decode(exported: &str) -> Result<String, String> {
+    let encoded = exported
+        .strip_prefix(EXPORT_PREFIX)
+        .ok_or("not a config exported by `myls config export`")?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| format!("corrupted config string: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("corrupted config string: {}", e))
+}
+
+// Fetching is delegated to curl, to keep an HTTP stack out of the binary.
+fn download(url: &str) -> Result<String, String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .map_err(|e| format!("could not run curl to download {}: {}", url, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "could not download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_column_priority() {
+        let config = Config::parse(r#"column_priority = ["name", "owner", "size"]"#).unwrap();
+        assert_eq!(
+            config.column_priority,
+            Some(vec![Column::Name, Column::Owner, Column::Size])
+        );
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let content = "icons = true\n[file_colors]\n\".py\" = \"38;5;220m\"\n";
+        let exported = format!("{}{}", EXPORT_PREFIX, URL_SAFE_NO_PAD.encode(content));
+
+        assert!(!exported.contains('\n'));
+        assert_eq!(decode(&exported).unwrap(), content);
+        assert!(decode("garbage").is_err());
+
+        let config = Config::parse(&decode(&exported).unwrap()).unwrap();
+        assert_eq!(config.icons, Some(true));
+        assert_eq!(config.file_colors.unwrap()[".py"], "38;5;220m");
+    }
+
+    #[test]
+    fn test_parse_ignore_patterns() {
+        let patterns = parse_ignore_patterns("# junk\n*.pyc\n\n__pycache__\n");
+        assert_eq!(patterns.len(), 2);
+        assert!(patterns[0].matches("module.pyc"));
+        assert!(patterns[1].matches("__pycache__"));
+    }
+
+    #[test]
+    fn test_parse_icons() {
+        let config = Config::parse("no_icons_for = [\"target\"]\nicon_placeholder = \"\"").unwrap();
+        assert_eq!(config.no_icons_for, Some(vec![IconKind::Target]));
+        assert_eq!(config.icon_placeholder.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Config::parse(r#"column_priority = ["nope"]"#).is_err());
+        assert!(Config::parse("unknown_key = 1").is_err());
+        assert!(Config::parse("").unwrap().column_priority.is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+use std::io::{self, BufWriter, Write};
+
+use crate::json;
+use crate::ProcessedInfo;
+
+/// Prints the entries as comma or tab separated values, after a header row.
+/// Errors found while scanning stay on stderr.
+pub fn print(processed_infos: &[ProcessedInfo], separator: char) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "{}", record(&json::FIELDS, separator))?;
+    for row in json::rows(processed_infos) {
+        writeln!(out, "{}", record(&row, separator))?;
+    }
+    out.flush()
+}
+
+/// Joins the fields into a line, quoting the ones that need it.
+pub fn record<S: AsRef<str>>(fields: &[S], separator: char) -> String {
+    fields
+        .iter()
+        .map(|field| quote(field.as_ref(), separator))
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())
+}
+
+// Fields holding the separator, quotes or line breaks are quoted, doubling their quotes (RFC 4180).
+fn quote(field: &str, separator: char) -> Cow<'_, str> {
+    if field.contains([separator, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        assert_eq!(record(&["a.txt", "12"], ','), "a.txt,12");
+        assert_eq!(record(&["a, b.txt", "12"], ','), "\"a, b.txt\",12");
+        assert_eq!(
+            record(&["say \"hi\"", "a,b"], '\t'),
+            "\"say \"\"hi\"\"\"\ta,b"
+        );
+        assert_eq!(record(&["tab\there"], '\t'), "\"tab\there\"");
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// A problem with a single entry that doesn't stop the listing (e.g. permission denied).
+#[derive(Serialize)]
+pub struct EntryError {
+    pub path: PathBuf,
+    /// Machine friendly category, e.g. "permission_denied" or "not_found"
+    pub kind: String,
+    pub message: String,
+    /// What was being done when the error happened, used in the human readable text
+    #[serde(skip)]
+    context: &'static str,
+}
+
+impl EntryError {
+    pub fn new(path: &Path, context: &'static str, error: &io::Error) -> Self {
+        EntryError {
+            path: path.to_path_buf(),
+            kind: error_kind(error),
+            message: error.to_string(),
+            context,
+        }
+    }
+}
+
+impl fmt::Display for EntryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {}", self.context, self.path.display(), self.message)
+    }
+}
+
+// Errors are printed right away, unless a machine readable output needs them in its own stream.
+static COLLECT: AtomicBool = AtomicBool::new(false);
+static COLLECTED: Mutex<Vec<EntryError>> = Mutex::new(Vec::new());
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Silences warnings and per-entry errors on stderr. Collected errors are still kept.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Prints a problem that doesn't stop myls (e.g. an invalid config file), unless quiet.
+pub fn warn(message: &str) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+/// Keeps reported errors to be retrieved with `take` instead of printing them to stderr.
+pub fn collect_errors(collect: bool) {
+    COLLECT.store(collect, Ordering::Relaxed);
+}
+
+pub fn report(error: EntryError) {
+    if COLLECT.load(Ordering::Relaxed) {
+        COLLECTED.lock().unwrap().push(error);
+    } else if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", error);
+    }
+}
+
+/// Prints the errors collected and not taken so far to stderr, unless quiet.
+pub fn print_collected() {
+    let errors = take();
+    if !QUIET.load(Ordering::Relaxed) {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+/// Returns (and forgets) the errors collected so far.
+pub fn take() -> Vec<EntryError> {
+    std::mem::take(&mut *COLLECTED.lock().unwrap())
+}
+
+fn error_kind(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => "permission_denied".to_string(),
+        io::ErrorKind::NotFound => "not_found".to_string(),
+        kind => format!("{:?}", kind).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_error() {
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+        let entry_error = EntryError::new(Path::new("/root/secret"), "Error accessing", &error);
+
+        assert_eq!(entry_error.kind, "permission_denied");
+        assert!(entry_error.to_string().starts_with("Error accessing /root/secret: "));
+    }
+}
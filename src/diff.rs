@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::report::{self, Row};
+use crate::theme::Theme;
+use crate::{
+    list_directory, print_listing, DisplayArgs, OutputFormat, ProcessedInfo, RawInfo, Settings,
+    COLOR_RESET,
+};
+
+#[derive(clap::Args)]
+pub struct DiffArgs {
+    /// Directory to compare from
+    left: String,
+
+    /// Directory to compare to
+    right: String,
+
+    /// Also show entries that are the same on both sides
+    #[arg(short, long)]
+    unchanged: bool,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+impl Change {
+    fn marker(&self, theme: &Theme) -> String {
+        match self {
+            Change::Added => format!("{}+{}", theme.added, COLOR_RESET),
+            Change::Removed => format!("{}-{}", theme.removed, COLOR_RESET),
+            Change::Modified => format!("{}~{}", theme.changed, COLOR_RESET),
+            Change::Unchanged => " ".to_string(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Modified => "modified",
+            Change::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// Machine readable version of a compared entry. The path is on the right side, unless the
+/// entry was removed from it.
+#[derive(Debug, PartialEq, Serialize)]
+struct ChangedEntry {
+    change: &'static str,
+    name: String,
+    path: String,
+}
+
+impl ChangedEntry {
+    fn new(change: &Change, raw_info: &RawInfo) -> Self {
+        ChangedEntry {
+            change: change.name(),
+            name: file_name(raw_info),
+            path: raw_info.path.display().to_string(),
+        }
+    }
+}
+
+impl Row for ChangedEntry {
+    const FIELDS: &'static [&'static str] = &["change", "name", "path"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.change.to_string(), self.name.clone(), self.path.clone()]
+    }
+}
+
+pub fn run(args: &DiffArgs, settings: &Settings) -> i32 {
+    for dir in [&args.left, &args.right] {
+        if !Path::new(dir).is_dir() {
+            eprintln!("Error: {} is not a directory", dir);
+            return 1;
+        }
+    }
+
+    // Pairs each name with its entry on the left and right sides
+    let mut entries: BTreeMap<String, (Option<RawInfo>, Option<RawInfo>)> = BTreeMap::new();
+    for raw_info in list_directory(Path::new(&args.left), &settings.scan) {
+        let name = file_name(&raw_info);
+        entries.entry(name).or_default().0 = Some(raw_info);
+    }
+    for raw_info in list_directory(Path::new(&args.right), &settings.scan) {
+        let name = file_name(&raw_info);
+        entries.entry(name).or_default().1 = Some(raw_info);
+    }
+
+    let changes: Vec<(Change, RawInfo)> = entries
+        .into_values()
+        .filter_map(|sides| match sides {
+            (Some(left), None) => Some((Change::Removed, left)),
+            (None, Some(right)) => Some((Change::Added, right)),
+            (Some(left), Some(right)) => Some((compare(&left, &right), right)),
+            (None, None) => None,
+        })
+        .filter(|(change, _)| args.unchanged || *change != Change::Unchanged)
+        .filter(|(_, raw_info)| settings.keep(raw_info))
+        .collect();
+
+    // Same order as a regular listing
+    let mut rows: Vec<(Change, ProcessedInfo)> = changes
+        .into_iter()
+        .map(|(change, raw_info)| (change, settings.process(raw_info)))
+        .collect();
+    rows.sort_by(|a, b| a.1.compare(&b.1, settings.sort_order));
+
+    if settings.print0 {
+        return print_changed_paths(&rows, settings);
+    }
+    if settings.output != OutputFormat::Table {
+        let entries: Vec<ChangedEntry> = rows
+            .iter()
+            .map(|(change, pinfo)| ChangedEntry::new(change, &pinfo.rinfo))
+            .collect();
+        return report::print(&entries, settings, |_| ());
+    }
+
+    let rows = rows
+        .into_iter()
+        .map(|(change, mut pinfo)| {
+            pinfo.name = format!("{} {}", change.marker(&settings.theme), pinfo.name);
+            pinfo
+        })
+        .collect();
+
+    print_listing(rows, settings)
+}
+
+// With -0, each path is preceded by its change (e.g. "added"), both ended by a NUL byte.
+fn print_changed_paths(rows: &[(Change, ProcessedInfo)], settings: &Settings) -> i32 {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = rows
+        .iter()
+        .try_for_each(|(change, pinfo)| {
+            out.write_all(change.name().as_bytes())?;
+            out.write_all(b"\0")?;
+            out.write_all(pinfo.rinfo.path.as_os_str().as_bytes())?;
+            out.write_all(b"\0")
+        })
+        .and_then(|()| out.flush());
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("Error: could not write output: {}", e);
+            1
+        }
+        _ => report::exit_code(settings, rows.is_empty()),
+    }
+}
+
+fn file_name(raw_info: &RawInfo) -> String {
+    raw_info
+        .path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Directories are compared by kind only, files also by size and modification time.
+fn compare(left: &RawInfo, right: &RawInfo) -> Change {
+    if left.is_directory != right.is_directory || left.is_symlink != right.is_symlink {
+        return Change::Modified;
+    }
+    if left.is_directory {
+        return Change::Unchanged;
+    }
+
+    let same_link_target =
+        !left.is_symlink || left.path.read_link().ok() == right.path.read_link().ok();
+    if left.size == right.size && left.modified_time == right.modified_time && same_link_target {
+        Change::Unchanged
+    } else {
+        Change::Modified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_raw_info;
+
+    #[test]
+    fn test_compare() {
+        let left = mock_raw_info("/tmp/file", 10, false);
+        let mut right = mock_raw_info("/tmp/file", 10, false);
+        right.modified_time = left.modified_time;
+        assert_eq!(compare(&left, &right), Change::Unchanged);
+
+        right.size = 20;
+        assert_eq!(compare(&left, &right), Change::Modified);
+
+        assert_eq!(compare(&left, &mock_raw_info("/tmp/file", 0, true)), Change::Modified);
+        let dir = |size| mock_raw_info("/tmp/dir", size, true);
+        assert_eq!(compare(&dir(1), &dir(2)), Change::Unchanged);
+    }
+
+    #[test]
+    fn test_changed_entry() {
+        let raw_info = mock_raw_info("/tmp/left/old.txt", 0, false);
+        let entry = ChangedEntry::new(&Change::Removed, &raw_info);
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"change":"removed","name":"old.txt","path":"/tmp/left/old.txt"}"#
+        );
+        assert_eq!(entry.fields(), ["removed", "old.txt", "/tmp/left/old.txt"]);
+    }
+}
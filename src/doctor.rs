@@ -0,0 +1,120 @@
+use std::env;
+
+/// Reports what the current terminal supports and which myls options fit it best.
+/// Some capabilities can't be queried reliably, so test glyphs are printed for the user to judge.
+pub fn run() -> i32 {
+    let is_tty = unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+    let term = env::var("TERM").unwrap_or_default();
+    let colors = color_support(&term, env::var("COLORTERM").ok().as_deref());
+    let locale = locale();
+    let utf8 = is_utf8(&locale);
+    let hyperlinks = hyperlink_support();
+
+    println!("Terminal");
+    report("stdout is a terminal", if is_tty { "yes" } else { "no (output is piped)" });
+    report("TERM", if term.is_empty() { "(unset)" } else { &term });
+    report("colors", colors);
+    report("hyperlinks (OSC 8)", if hyperlinks { "likely" } else { "unknown" });
+
+    println!("\nLocale");
+    report("character set", if locale.is_empty() { "(unset)" } else { &locale });
+    report("UTF-8", if utf8 { "yes" } else { "no" });
+
+    println!("\nGlyphs (check how they render)");
+    report(
+        "colors",
+        "\x1b[32mgreen\x1b[0m \x1b[38;5;33m256-blue\x1b[0m \x1b[38;2;255;128;0mtruecolor-orange\x1b[0m",
+    );
+    report("emoji width", "|📁|📂| <- the bars should be evenly spaced, two cells per icon");
+    report("Nerd Font", "|\u{f07b}|\u{e7a8}| <- folder and Rust logos, not boxes or question marks");
+
+    println!("\nSuggestions");
+    for suggestion in suggestions(utf8, colors) {
+        println!("  - {}", suggestion);
+    }
+
+    0
+}
+
+fn report(label: &str, value: &str) {
+    println!("  {:<22} {}", label, value);
+}
+
+fn color_support(term: &str, colorterm: Option<&str>) -> &'static str {
+    if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+        "truecolor"
+    } else if term.contains("256color") {
+        "256"
+    } else if term.is_empty() || term == "dumb" {
+        "none"
+    } else {
+        "16"
+    }
+}
+
+// Same precedence as setlocale(3): LC_ALL, then LC_CTYPE, then LANG.
+fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+fn is_utf8(locale: &str) -> bool {
+    let locale = locale.to_lowercase();
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+// There's no standard query for OSC 8, so it's guessed from terminals known to support it.
+fn hyperlink_support() -> bool {
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+    let vte_version: u32 = env::var("VTE_VERSION").ok().and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    matches!(term_program.as_str(), "iTerm.app" | "WezTerm" | "vscode" | "ghostty")
+        || vte_version >= 5000
+        || env::var_os("KITTY_WINDOW_ID").is_some()
+        || env::var_os("WT_SESSION").is_some()
+}
+
+fn suggestions(utf8: bool, colors: &str) -> Vec<&'static str> {
+    let mut suggestions = Vec::new();
+
+    if utf8 {
+        suggestions.push("Icons should work: try --icons (or `icons = true` in the config file).");
+    } else {
+        suggestions.push("Set a UTF-8 locale (e.g. LANG=en_US.UTF-8) before using --icons.");
+    }
+
+    match colors {
+        "none" => {
+            suggestions.push("This terminal reports no color support, output may look garbled.")
+        }
+        "16" => suggestions.push(
+            "Only basic colors detected: set TERM to a 256color variant if the terminal supports it.",
+        ),
+        _ => {}
+    }
+
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_support() {
+        assert_eq!(color_support("xterm-256color", Some("truecolor")), "truecolor");
+        assert_eq!(color_support("xterm-256color", None), "256");
+        assert_eq!(color_support("linux", None), "16");
+        assert_eq!(color_support("dumb", None), "none");
+    }
+
+    #[test]
+    fn test_is_utf8() {
+        assert!(is_utf8("en_US.UTF-8"));
+        assert!(is_utf8("pt_BR.utf8"));
+        assert!(!is_utf8("C"));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cache::{DirContents, SizeCache};
+use crate::diagnostics;
+use crate::source::LocalFs;
+use crate::{print_listing, scan_paths, DisplayArgs, Settings};
+
+#[derive(clap::Args)]
+pub struct DuArgs {
+    /// Files or directories to measure (default: current directory)
+    #[arg(default_value = ".")]
+    paths: Vec<String>,
+
+    /// Reuse the contents of directories not modified since the last cached run.
+    /// Files rewritten in place don't update their directory, run `myls cache clear` to start over.
+    #[arg(long)]
+    cached: bool,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+pub fn run(args: &DuArgs, settings: &Settings) -> i32 {
+    let paths = crate::resolve_paths(&args.paths);
+
+    let mut raw_infos = match scan_paths(&paths, &settings.scan, &LocalFs) {
+        Some(raw_infos) => raw_infos,
+        None => return 1,
+    };
+    raw_infos.retain(|raw_info| settings.keep(raw_info));
+
+    let mut cache = if args.cached {
+        Some(SizeCache::load())
+    } else {
+        None
+    };
+    for raw_info in raw_infos.iter_mut() {
+        if raw_info.is_directory {
+            raw_info.dir_size = Some(match cache.as_mut() {
+                Some(cache) => cached_dir_size(&raw_info.path, cache),
+                None => dir_size(&raw_info.path),
+            });
+        }
+    }
+    if let Some(Err(e)) = cache.map(|cache| cache.save()) {
+        diagnostics::warn(&format!("could not save the size cache: {}", e));
+    }
+
+    // Biggest entries first (smallest with --reverse), keeping the main dir on top
+    raw_infos.sort_by_key(|r| (!r.is_main_dir, std::cmp::Reverse(r.dir_size.unwrap_or(r.size))));
+    if settings.sort_order.reverse {
+        let first_entry = raw_infos.iter().take_while(|r| r.is_main_dir).count();
+        raw_infos[first_entry..].reverse();
+    }
+    let processed_infos = raw_infos.into_iter().map(|r| settings.process(r)).collect();
+
+    print_listing(processed_infos, settings)
+}
+
+/// Most directories walked at the same time by `dir_sizes`.
+const MAX_THREADS: usize = 8;
+
+/// Total size of the files under a directory. Symlinks aren't followed, hard links are counted
+/// once, and unreadable entries are skipped.
+pub fn dir_size(directory: &Path) -> u64 {
+    Walk::new(None, None).sum_sizes(directory)
+}
+
+/// Total sizes of several directories, as `dir_size` computes them, walked in parallel.
+/// Walks still going after the timeout stop there: their size is the part found so far,
+/// flagged as incomplete. Timeouts too long to be reached don't limit the walks.
+pub fn dir_sizes(directories: &[PathBuf], timeout: Option<Duration>) -> Vec<(u64, bool)> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let threads = thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(MAX_THREADS)
+        .min(directories.len());
+    let next = AtomicUsize::new(0);
+
+    let mut sizes = vec![(0, true); directories.len()];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut sizes = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(directory) = directories.get(i) else {
+                            return sizes;
+                        };
+                        let mut walk = Walk::new(None, deadline);
+                        let size = walk.sum_sizes(directory);
+                        sizes.push((i, (size, !walk.cut)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, size) in worker.join().unwrap_or_default() {
+                sizes[i] = size;
+            }
+        }
+    });
+    sizes
+}
+
+/// Total size of the files directly inside a directory, without going into subdirectories.
+pub fn shallow_dir_size(directory: &Path) -> u64 {
+    let contents = read_contents(directory);
+    contents.files_size + contents.hard_links.iter().map(|&(_, _, size)| size).sum::<u64>()
+}
+
+/// Same as `dir_size`, reading only the directories whose cached contents are outdated.
+pub fn cached_dir_size(directory: &Path, cache: &mut SizeCache) -> u64 {
+    // Cache keys are absolute, so the same directory is found from anywhere
+    match fs::canonicalize(directory) {
+        Ok(directory) => Walk::new(Some(cache), None).sum_sizes(&directory),
+        Err(_) => 0,
+    }
+}
+
+// A walk through a directory tree, summing the sizes of its files.
+struct Walk<'a> {
+    cache: Option<&'a mut SizeCache>,
+    seen_inodes: HashSet<(u64, u64)>,
+    deadline: Option<Instant>,
+    // Whether directories were left out, the deadline having passed
+    cut: bool,
+}
+
+impl<'a> Walk<'a> {
+    fn new(cache: Option<&'a mut SizeCache>, deadline: Option<Instant>) -> Self {
+        Walk {
+            cache,
+            seen_inodes: HashSet::new(),
+            deadline,
+            cut: false,
+        }
+    }
+
+    fn sum_sizes(&mut self, directory: &Path) -> u64 {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.cut = true;
+            return 0;
+        }
+        let contents = match self.cache.as_deref_mut() {
+            Some(cache) => {
+                let mtime = match directory.symlink_metadata() {
+                    Ok(metadata) => (metadata.mtime(), metadata.mtime_nsec()),
+                    Err(_) => return 0,
+                };
+                match cache.get(directory, mtime) {
+                    Some(contents) => contents.clone(),
+                    None => {
+                        let contents = read_contents(directory);
+                        cache.insert(directory, mtime, contents.clone());
+                        contents
+                    }
+                }
+            }
+            None => read_contents(directory),
+        };
+
+        let mut total = contents.files_size;
+        for (dev, ino, size) in contents.hard_links {
+            if self.seen_inodes.insert((dev, ino)) {
+                total += size;
+            }
+        }
+        for subdir in contents.subdirs {
+            total += self.sum_sizes(&directory.join(subdir));
+        }
+        total
+    }
+}
+
+fn read_contents(directory: &Path) -> DirContents {
+    let mut contents = DirContents::default();
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return contents,
+    };
+
+    for entry in entries.flatten() {
+        let metadata = match entry.path().symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+
+        if metadata.is_dir() {
+            contents.subdirs.push(entry.file_name().into());
+        } else if metadata.nlink() <= 1 {
+            contents.files_size += metadata.len();
+        } else {
+            contents.hard_links.push((metadata.dev(), metadata.ino(), metadata.len()));
+        }
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_size() {
+        let dir = crate::tests::TempDir::new("du");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a"), [0u8; 100]).unwrap();
+        fs::write(dir.join("sub/b"), [0u8; 50]).unwrap();
+        fs::hard_link(dir.join("a"), dir.join("sub/a-link")).unwrap();
+
+        assert_eq!(dir_size(&dir), 150);
+        assert_eq!(shallow_dir_size(&dir), 100);
+        let sizes = dir_sizes(&[dir.clone(), dir.join("sub")], None);
+        assert_eq!(sizes, [(150, true), (150, true)]);
+        // Walks out of time keep what they found, here nothing
+        assert_eq!(dir_sizes(std::slice::from_ref(&*dir), Some(Duration::ZERO)), [(0, false)]);
+        assert_eq!(dir_sizes(std::slice::from_ref(&*dir), Some(Duration::MAX)), [(150, true)]);
+
+        let mut cache = SizeCache::default();
+        assert_eq!(cached_dir_size(&dir, &mut cache), 150);
+        // Unmodified directories are taken from the cache
+        fs::write(dir.join("sub/b"), [0u8; 10]).unwrap();
+        assert_eq!(cached_dir_size(&dir, &mut cache), 150);
+        // Stand in for a modification of sub, whose mtime may not have ticked yet
+        fs::write(dir.join("sub/c"), [0u8; 10]).unwrap();
+        let sub = fs::canonicalize(dir.join("sub")).unwrap();
+        cache.insert(&sub, (0, 0), DirContents::default());
+        assert_eq!(cached_dir_size(&dir, &mut cache), 120);
+    }
+}
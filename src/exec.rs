@@ -0,0 +1,252 @@
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::process::Command;
+
+use log::{debug, info};
+
+/// Placeholder replaced by the path of an entry in a command's arguments.
+const PLACEHOLDER: &str = "{}";
+
+/// Upper bound on the bytes of paths given to a single batch, well under the usual ARG_MAX.
+const BATCH_BYTES: usize = 128 * 1024;
+
+/// A command run on the listed entries, given as a single string (e.g. "gzip -k {}").
+/// It's split into words as a shell would, but never run through one, so names with spaces,
+/// quotes or `$` reach the command as they are.
+pub struct Exec {
+    words: Vec<String>,
+    batch: bool,
+}
+
+impl Exec {
+    pub fn parse(command: &str, batch: bool) -> Result<Self, String> {
+        let words = split_words(command)?;
+        if words.is_empty() {
+            return Err("empty command".to_string());
+        }
+        Ok(Exec { words, batch })
+    }
+
+    /// Runs the command on each path, or on batches of them, and returns the exit code:
+    /// 1 if a command couldn't run or failed. Any command may delete or change the entries
+    /// (e.g. `sudo rm`, `sh -c`, `sed -i`, `gzip`), so it needs `confirmed` (from --yes) or an
+    /// answer on the terminal.
+    pub fn run(&self, paths: &[PathBuf], confirmed: bool) -> i32 {
+        if paths.is_empty() {
+            return 0;
+        }
+        let stdin = io::stdin();
+        if !confirmed && !self.confirm(paths.len(), stdin.lock(), stdin.is_terminal()) {
+            eprintln!("Error: {} not run, pass --yes to confirm", self.words[0]);
+            return 1;
+        }
+
+        let mut failed = false;
+        if self.batch {
+            for batch in batches(paths) {
+                failed |= !self.spawn(self.batch_args(batch));
+            }
+        } else {
+            for path in paths {
+                failed |= !self.spawn(self.args(path.as_os_str()));
+            }
+        }
+        i32::from(failed)
+    }
+
+    // Asks on the terminal, refusing when there's none to ask (e.g. in scripts)
+    fn confirm(&self, count: usize, mut input: impl BufRead, interactive: bool) -> bool {
+        if !interactive {
+            return false;
+        }
+        let noun = if count == 1 { "entry" } else { "entries" };
+        eprint!("Run {} on {} {}? [y/N] ", self.words[0], count, noun);
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer).is_err() {
+            return false;
+        }
+        matches!(answer.trim(), "y" | "Y" | "yes")
+    }
+
+    // Arguments for a single path, appended if no argument holds the placeholder
+    fn args(&self, path: &OsStr) -> Vec<OsString> {
+        let mut args: Vec<OsString> = self.words[1..]
+            .iter()
+            .map(|word| substitute(word, path))
+            .collect();
+        if !self.words.iter().any(|word| word.contains(PLACEHOLDER)) {
+            args.push(path.to_os_string());
+        }
+        args
+    }
+
+    // Arguments for a batch of paths, all of them taking the place of a "{}" argument
+    fn batch_args(&self, paths: &[PathBuf]) -> Vec<OsString> {
+        let mut args = Vec::new();
+        let mut substituted = false;
+        for word in &self.words[1..] {
+            if word == PLACEHOLDER {
+                args.extend(paths.iter().map(|path| path.as_os_str().to_os_string()));
+                substituted = true;
+            } else {
+                args.push(OsString::from(word));
+            }
+        }
+        if !substituted {
+            args.extend(paths.iter().map(|path| path.as_os_str().to_os_string()));
+        }
+        args
+    }
+
+    // Runs the program with its output going to ours, returning whether it succeeded
+    fn spawn(&self, args: Vec<OsString>) -> bool {
+        debug!("Running {} with {:?}", self.words[0], args);
+        match Command::new(&self.words[0]).args(&args).status() {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                info!("{} exited with {}", self.words[0], status);
+                false
+            }
+            Err(e) => {
+                eprintln!("Error: could not run {}: {}", self.words[0], e);
+                false
+            }
+        }
+    }
+}
+
+// Replaces every placeholder in the word, keeping the path's bytes as they are.
+fn substitute(word: &str, path: &OsStr) -> OsString {
+    let mut bytes = Vec::new();
+    for (i, part) in word.split(PLACEHOLDER).enumerate() {
+        if i > 0 {
+            bytes.extend_from_slice(path.as_bytes());
+        }
+        bytes.extend_from_slice(part.as_bytes());
+    }
+    OsString::from_vec(bytes)
+}
+
+// Groups paths so that no batch gets more than BATCH_BYTES of them.
+fn batches(paths: &[PathBuf]) -> Vec<&[PathBuf]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    for (i, path) in paths.iter().enumerate() {
+        let len = path.as_os_str().len() + 1;
+        if i > start && bytes + len > BATCH_BYTES {
+            batches.push(&paths[start..i]);
+            start = i;
+            bytes = 0;
+        }
+        bytes += len;
+    }
+    batches.push(&paths[start..]);
+    batches
+}
+
+// Splits a command into words as POSIX shells do with quotes and backslashes,
+// without any expansion.
+fn split_words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unclosed double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        let words = split_words(r#"grep -l "TODO: fix" '{}' a\ b "" x"\"y""#).unwrap();
+        assert_eq!(words, ["grep", "-l", "TODO: fix", "{}", "a b", "", "x\"y"]);
+        assert!(split_words("echo 'oops").is_err());
+        assert!(split_words("echo \"oops").is_err());
+    }
+
+    #[test]
+    fn test_args() {
+        let exec = Exec::parse("cp {} {}.bak", false).unwrap();
+        let args = exec.args(OsStr::new("my $file"));
+        assert_eq!(args, ["my $file", "my $file.bak"]);
+
+        let exec = Exec::parse("wc -l", false).unwrap();
+        assert_eq!(exec.args(OsStr::new("a")), ["-l", "a"]);
+
+        let paths = [PathBuf::from("a"), PathBuf::from("b")];
+        let exec = Exec::parse("tar cf out.tar {}", true).unwrap();
+        assert_eq!(exec.batch_args(&paths), ["cf", "out.tar", "a", "b"]);
+        let exec = Exec::parse("du -sh", true).unwrap();
+        assert_eq!(exec.batch_args(&paths), ["-sh", "a", "b"]);
+    }
+
+    #[test]
+    fn test_confirm() {
+        // Wrapped or indirect deletions are asked about as any other command
+        let commands = [
+            "/bin/rm -f",
+            "sudo rm {}",
+            "env rm",
+            "xargs rm",
+            "sh -c 'rm \"$0\"' {}",
+            "find {} -delete",
+            "sed -i s/a/b/",
+            "gzip",
+            "wc -l",
+        ];
+        for command in commands {
+            let exec = Exec::parse(command, false).unwrap();
+            assert!(!exec.confirm(1, io::empty(), false), "{}", command);
+            assert!(!exec.confirm(2, "n\n".as_bytes(), true), "{}", command);
+            assert!(!exec.confirm(2, "".as_bytes(), true), "{}", command);
+            assert!(exec.confirm(2, "y\n".as_bytes(), true), "{}", command);
+        }
+    }
+}
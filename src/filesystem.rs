@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+/// Filesystems storing no POSIX permissions, whose mode bits are made up from mount options.
+const WITHOUT_PERMISSIONS: [&str; 4] = ["vfat", "msdos", "fat", "exfat"];
+
+/// A mounted filesystem.
+pub struct Mount {
+    mount_point: PathBuf,
+    /// Type of the filesystem, as the kernel names it (e.g. "ext4", "vfat")
+    pub fs_type: String,
+    /// Mounted read-only
+    pub read_only: bool,
+}
+
+impl Mount {
+    /// Whether the mode bits of its entries are real ones.
+    pub fn has_permissions(&self) -> bool {
+        !WITHOUT_PERMISSIONS.contains(&self.fs_type.as_str())
+    }
+
+    /// Stands for the permissions of an entry on a filesystem without any: "ro" when it can't
+    /// be written (the read-only attribute of FAT, or a read-only mount), "-" otherwise.
+    pub fn attributes(&self, mode: u32) -> &'static str {
+        if self.read_only || mode & 0o222 == 0 {
+            "ro"
+        } else {
+            "-"
+        }
+    }
+}
+
+/// Filesystems mounted for the current process, read from `/proc/self/mountinfo`.
+#[derive(Default)]
+pub struct Mounts {
+    mounts: Vec<Mount>,
+}
+
+impl Mounts {
+    pub fn load() -> Self {
+        match fs::read_to_string("/proc/self/mountinfo") {
+            Ok(mountinfo) => Self::parse(&mountinfo),
+            Err(e) => {
+                debug!("Could not read mount points: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    // Lines look like "36 35 8:17 / /media/usb rw,nosuid - vfat /dev/sdb1 rw,fmask=0022"
+    fn parse(mountinfo: &str) -> Self {
+        let mut mounts = Vec::new();
+        for line in mountinfo.lines() {
+            let (mount_fields, fs_fields) = match line.split_once(" - ") {
+                Some(fields) => fields,
+                None => continue,
+            };
+            let mut mount_fields = mount_fields.split(' ').skip(4);
+            let (mount_point, options) = match (mount_fields.next(), mount_fields.next()) {
+                (Some(mount_point), Some(options)) => (mount_point, options),
+                _ => continue,
+            };
+            let fs_type = match fs_fields.split(' ').next() {
+                Some(fs_type) => fs_type,
+                None => continue,
+            };
+            mounts.push(Mount {
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: fs_type.to_string(),
+                read_only: options.split(',').any(|option| option == "ro"),
+            });
+        }
+        Mounts { mounts }
+    }
+
+    /// Filesystem the entry is on, the one mounted on it for mount points.
+    pub fn find(&self, path: &Path) -> Option<&Mount> {
+        // The entry itself may be a link, only its directory is resolved
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).ok()?.join(name),
+            _ => fs::canonicalize(path).ok()?,
+        };
+        // Later mounts hide earlier ones on the same mount point, max_by_key keeping the last
+        self.mounts
+            .iter()
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.as_os_str().len())
+    }
+
+    /// Filesystem of the entry when it has no permissions (e.g. a FAT USB stick).
+    /// Paths are only resolved when such a filesystem is mounted at all.
+    pub fn without_permissions(&self, path: &Path) -> Option<&Mount> {
+        if self.mounts.iter().all(Mount::has_permissions) {
+            return None;
+        }
+        self.find(path).filter(|mount| !mount.has_permissions())
+    }
+}
+
+/// Undoes the octal escapes of spaces, tabs, newlines and backslashes in mountinfo
+/// (e.g. "\040").
+pub fn unescape(field: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = field;
+    while let Some(pos) = rest.find('\\') {
+        unescaped.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 4).and_then(|c| u8::from_str_radix(c, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(code as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[pos + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_without_permissions() {
+        let dir = crate::tests::TempDir::new("filesystem");
+        fs::create_dir_all(dir.join("usb stick")).unwrap();
+        fs::create_dir_all(dir.join("disk")).unwrap();
+        let dir = fs::canonicalize(&dir).unwrap();
+        let mountinfo = format!(
+            "22 1 8:1 / / rw - ext4 /dev/sda1 rw\n\
+             40 22 8:17 / {} rw,nosuid - vfat /dev/sdb1 rw,fmask=0022\n\
+             41 22 8:33 / {} ro - exfat /dev/sdc1 ro\n",
+            dir.join("usb stick").display().to_string().replace(' ', "\\040"),
+            dir.join("disk").display(),
+        );
+        let mounts = Mounts::parse(&mountinfo);
+
+        let usb = mounts.without_permissions(&dir.join("usb stick/photo.jpg")).unwrap();
+        assert_eq!(usb.fs_type, "vfat");
+        assert_eq!(usb.attributes(0o755), "-");
+        assert_eq!(usb.attributes(0o555), "ro");
+        // The mount point shows the root of the stick
+        assert!(mounts.without_permissions(&dir.join("usb stick")).is_some());
+        assert_eq!(mounts.find(&dir.join("disk/a")).unwrap().attributes(0o755), "ro");
+        assert!(mounts.without_permissions(&dir).is_none());
+        assert_eq!(mounts.find(&dir).unwrap().fs_type, "ext4");
+    }
+
+    #[test]
+    fn test_stacked_mounts() {
+        // Automounted like /boot/efi: autofs first, the filesystem mounted over it later
+        let dir = fs::canonicalize(std::env::temp_dir()).unwrap();
+        let mountinfo = format!(
+            "22 1 8:1 / / rw - ext4 /dev/sda1 rw\n\
+             30 22 0:40 / {0} rw - autofs systemd-1 rw,fd=51\n\
+             31 30 8:2 / {0} rw - vfat /dev/sda2 rw,fmask=0077\n",
+            dir.display()
+        );
+        let mounts = Mounts::parse(&mountinfo);
+
+        let mount = mounts.find(&dir.join("EFI")).unwrap();
+        assert_eq!(mount.fs_type, "vfat");
+        assert!(!mount.has_permissions());
+    }
+}
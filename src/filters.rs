@@ -0,0 +1,573 @@
+use std::fs;
+use std::ops::Range;
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
+use glob::Pattern;
+use users::{get_group_by_name, get_user_by_name};
+
+use crate::category::Category;
+use crate::git::{GitState, GitStatusCache};
+use crate::RawInfo;
+
+/// Options restricting which entries are listed.
+#[derive(clap::Args)]
+pub struct FilterArgs {
+    /// Show hidden files (starting with ., flagged hidden by the OS or listed in a .hidden file)
+    #[arg(short, long)]
+    pub all: bool,
+
+    /// Don't hide entries matching the global ignore file (~/.config/myls/ignore)
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Only list entries whose names match the glob pattern (e.g. "*.rs"), directories included.
+    /// Can be repeated to match any of several patterns.
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub matches: Vec<Pattern>,
+
+    /// Hide entries whose names match the glob pattern (e.g. "target" or "*.o"), even with --all.
+    /// Can be repeated to exclude several patterns.
+    #[arg(long, value_name = "PATTERN")]
+    pub exclude: Vec<Pattern>,
+
+    /// Only list entries of the given kinds (f: regular files, d: directories, l: symlinks,
+    /// x: executables) or files of the given categories (guessed from the file extension).
+    /// Accepts several values, separated by commas.
+    #[arg(long = "type", value_enum, value_delimiter = ',')]
+    pub types: Vec<TypeFilter>,
+
+    /// Only list entries of at least the given size, e.g. 10M (units: B, K, M, G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only list entries of at most the given size, e.g. 1G (units: B, K, M, G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Only list entries modified more recently than a duration ago (e.g. 30m, 2d, 6mo; units:
+    /// s, m, h, d, w, mo, y) or than a date (YYYY-MM-DD, optionally followed by HH:MM)
+    #[arg(long, value_name = "WHEN", value_parser = parse_time_limit)]
+    pub newer_than: Option<TimeLimit>,
+
+    /// Only list entries modified before a duration ago or a date, as in --newer-than
+    #[arg(long, value_name = "WHEN", value_parser = parse_time_limit)]
+    pub older_than: Option<TimeLimit>,
+
+    /// Only list entries modified after the given file was, as `find -newer` does
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser = parse_reference_time,
+        conflicts_with_all = ["newer_than", "today", "this_week", "this_month"]
+    )]
+    pub newer_than_file: Option<DateTime<Local>>,
+
+    /// Only list entries modified before the given file was
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser = parse_reference_time,
+        conflicts_with = "older_than"
+    )]
+    pub older_than_file: Option<DateTime<Local>>,
+
+    /// Only list entries modified since midnight
+    #[arg(long, conflicts_with_all = ["newer_than", "this_week", "this_month"])]
+    pub today: bool,
+
+    /// Only list entries modified since Monday, midnight
+    #[arg(long, conflicts_with_all = ["newer_than", "this_month"])]
+    pub this_week: bool,
+
+    /// Only list entries modified since the first day of the month, midnight
+    #[arg(long, conflicts_with = "newer_than")]
+    pub this_month: bool,
+
+    /// Only list entries owned by the given user, by name or numeric id
+    #[arg(long, value_name = "USER", value_parser = parse_user)]
+    pub owner: Option<u32>,
+
+    /// Only list entries belonging to the given group, by name or numeric id
+    #[arg(long, value_name = "GROUP", value_parser = parse_group)]
+    pub group: Option<u32>,
+
+    /// Only list entries in the given git states (directories match through their contents).
+    /// Accepts several values, separated by commas.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub git_filter: Vec<GitState>,
+
+    /// Hide entries ignored by git (.gitignore, .git/info/exclude or the global excludes file),
+    /// unless --all is given
+    #[arg(long)]
+    pub git_ignore: bool,
+}
+
+/// Kind of entry, as told by its metadata.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum EntryKind {
+    #[value(name = "f", alias = "file")]
+    File,
+    #[value(name = "d", alias = "dir")]
+    Directory,
+    #[value(name = "l", alias = "link")]
+    Symlink,
+    #[value(name = "x", alias = "exec")]
+    Executable,
+}
+
+impl EntryKind {
+    fn matches(self, raw_info: &RawInfo) -> bool {
+        let regular_file = !raw_info.is_directory && !raw_info.is_symlink && !raw_info.vanished;
+        match self {
+            EntryKind::File => regular_file,
+            EntryKind::Directory => raw_info.is_directory,
+            EntryKind::Symlink => raw_info.is_symlink,
+            EntryKind::Executable => regular_file && raw_info.is_executable,
+        }
+    }
+}
+
+/// A value of `--type`: either a kind of entry or a category of file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TypeFilter {
+    Kind(EntryKind),
+    Category(Category),
+}
+
+impl ValueEnum for TypeFilter {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            TypeFilter::Kind(EntryKind::File),
+            TypeFilter::Kind(EntryKind::Directory),
+            TypeFilter::Kind(EntryKind::Symlink),
+            TypeFilter::Kind(EntryKind::Executable),
+            TypeFilter::Category(Category::Image),
+            TypeFilter::Category(Category::Video),
+            TypeFilter::Category(Category::Audio),
+            TypeFilter::Category(Category::Archive),
+            TypeFilter::Category(Category::Code),
+            TypeFilter::Category(Category::Document),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            TypeFilter::Kind(kind) => kind.to_possible_value(),
+            TypeFilter::Category(category) => category.to_possible_value(),
+        }
+    }
+}
+
+/// Limit of --newer-than and --older-than: a date, or a duration before now.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeLimit {
+    At(DateTime<Local>),
+    Ago(Duration),
+}
+
+impl TimeLimit {
+    /// The point in time, durations counting back from now (frozen by --deterministic).
+    /// Durations reaching before the earliest representable time stop there.
+    fn resolve(self, now: DateTime<Local>) -> DateTime<Local> {
+        match self {
+            TimeLimit::At(time) => time,
+            TimeLimit::Ago(duration) => now
+                .checked_sub_signed(duration)
+                .unwrap_or_else(|| DateTime::<Utc>::MIN_UTC.with_timezone(&Local)),
+        }
+    }
+}
+
+/// Calendar period of --today, --this-week and --this-month.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    fn from_args(args: &FilterArgs) -> Option<Self> {
+        match (args.today, args.this_week, args.this_month) {
+            (true, _, _) => Some(Period::Day),
+            (_, true, _) => Some(Period::Week),
+            (_, _, true) => Some(Period::Month),
+            _ => None,
+        }
+    }
+
+    // First day of the period the date is in, weeks starting on Monday
+    fn first_day(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday().into()),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// Midnight of the period's first day, in the local time zone.
+    fn start(self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let first_day = self.first_day(now.date_naive());
+        Local.from_local_datetime(&first_day.and_hms_opt(0, 0, 0)?).earliest()
+    }
+}
+
+/// Filters resolved from the command line, applied to entries after their metadata is read.
+pub struct Filters {
+    kinds: Vec<EntryKind>,
+    types: Vec<Category>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    newer_than: Option<DateTime<Local>>,
+    older_than: Option<DateTime<Local>>,
+    owner: Option<u32>,
+    group: Option<u32>,
+    git_states: Vec<GitState>,
+    git_ignore: bool,
+    git_status: GitStatusCache,
+}
+
+impl Filters {
+    pub fn new(args: &FilterArgs, now: DateTime<Local>) -> Self {
+        Filters {
+            kinds: args
+                .types
+                .iter()
+                .filter_map(|t| match t {
+                    TypeFilter::Kind(kind) => Some(*kind),
+                    TypeFilter::Category(_) => None,
+                })
+                .collect(),
+            types: args
+                .types
+                .iter()
+                .filter_map(|t| match t {
+                    TypeFilter::Category(category) => Some(*category),
+                    TypeFilter::Kind(_) => None,
+                })
+                .collect(),
+            min_size: args.min_size,
+            max_size: args.max_size,
+            newer_than: args
+                .newer_than
+                .map(|limit| limit.resolve(now))
+                .or(args.newer_than_file)
+                .or_else(|| Period::from_args(args)?.start(now)),
+            older_than: args
+                .older_than
+                .map(|limit| limit.resolve(now))
+                .or(args.older_than_file),
+            owner: args.owner,
+            group: args.group,
+            git_states: args.git_filter.clone(),
+            git_ignore: args.git_ignore && !args.all,
+            git_status: GitStatusCache::default(),
+        }
+    }
+
+    /// Whether the entry passes every active filter.
+    /// Kinds and categories are checked separately, so `--type x,code` lists executable code files.
+    pub fn matches(&self, raw_info: &RawInfo) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|kind| kind.matches(raw_info)) {
+            return false;
+        }
+
+        if !self.types.is_empty() {
+            let category = if raw_info.is_directory {
+                None
+            } else {
+                Category::of(&raw_info.path)
+            };
+            if !category.is_some_and(|c| self.types.contains(&c)) {
+                return false;
+            }
+        }
+
+        // Like find, directories are compared by their own size, not their contents'
+        if self.min_size.is_some_and(|min| raw_info.size < min)
+            || self.max_size.is_some_and(|max| raw_info.size > max)
+        {
+            return false;
+        }
+
+        if self.newer_than.is_some_and(|limit| raw_info.modified_time <= limit)
+            || self.older_than.is_some_and(|limit| raw_info.modified_time >= limit)
+        {
+            return false;
+        }
+
+        if self.owner.is_some_and(|uid| raw_info.owner_uid != uid)
+            || self.group.is_some_and(|gid| raw_info.group_gid != gid)
+        {
+            return false;
+        }
+
+        if !self.git_states.is_empty() {
+            let states = self.git_status.states(&raw_info.path);
+            if !self.git_states.iter().any(|s| states.contains(s)) {
+                return false;
+            }
+        }
+
+        if self.git_ignore && self.git_status.is_ignored(&raw_info.path) {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Parses a size such as "512", "1.5K" or "10M" into bytes. Units are powers of 1024, as displayed.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let unit_start = upper.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(unit_start);
+
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown unit in {} (expected B, K, M or G)", s)),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("invalid size {}", s))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses a duration before now (e.g. "2d" or "6mo") or a local date.
+/// Months count as 30 days and years as 365.
+fn parse_time_limit(s: &str) -> Result<TimeLimit, String> {
+    let s = s.trim();
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, format) {
+            return local_time(naive, s).map(TimeLimit::At);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local_time(date.and_hms_opt(0, 0, 0).unwrap(), s).map(TimeLimit::At);
+    }
+
+    let unit_start = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(unit_start);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("expected a duration (e.g. 2d) or a date (YYYY-MM-DD), got {}", s))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(number),
+        "m" | "min" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        "mo" => number.checked_mul(30).and_then(Duration::try_days),
+        "y" => number.checked_mul(365).and_then(Duration::try_days),
+        _ => return Err(format!("unknown unit in {} (expected s, m, h, d, w, mo or y)", s)),
+    };
+    duration
+        .map(TimeLimit::Ago)
+        .ok_or_else(|| format!("duration {} is too long", s))
+}
+
+// Modification time of a reference file, links being followed.
+fn parse_reference_time(s: &str) -> Result<DateTime<Local>, String> {
+    fs::metadata(s)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Local>::from)
+        .map_err(|e| format!("could not read {}: {}", s, e))
+}
+
+fn local_time(naive: NaiveDateTime, s: &str) -> Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", s))
+}
+
+fn parse_user(s: &str) -> Result<u32, String> {
+    s.parse()
+        .ok()
+        .or_else(|| get_user_by_name(s).map(|user| user.uid()))
+        .ok_or_else(|| format!("unknown user {}", s))
+}
+
+fn parse_group(s: &str) -> Result<u32, String> {
+    s.parse()
+        .ok()
+        .or_else(|| get_group_by_name(s).map(|group| group.gid()))
+        .ok_or_else(|| format!("unknown group {}", s))
+}
+
+/// Byte ranges of `name` covered by the literal parts of a glob pattern the name matches,
+/// used to highlight what a name filter matched. Wildcards and character classes are not highlighted.
+/// Literals at either end of the pattern are anchored to that end of the name, the others are
+/// taken at their first occurrence.
+pub fn match_ranges(pattern: &Pattern, name: &str) -> Vec<Range<usize>> {
+    if !pattern.matches(name) {
+        return Vec::new();
+    }
+
+    let is_wildcard = |c: Option<char>| matches!(c, Some('*' | '?' | '[' | ']'));
+    let anchored_start = !is_wildcard(pattern.as_str().chars().next());
+    let anchored_end = !is_wildcard(pattern.as_str().chars().next_back());
+
+    let literals = pattern_literals(pattern.as_str());
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (i, literal) in literals.iter().enumerate() {
+        let begin = if i == 0 && anchored_start {
+            Some(0)
+        } else if i == literals.len() - 1 && anchored_end {
+            name.len()
+                .checked_sub(literal.len())
+                .filter(|&begin| begin >= start && name.is_char_boundary(begin))
+        } else {
+            name[start..].find(literal.as_str()).map(|pos| start + pos)
+        };
+        match begin {
+            Some(begin) => {
+                start = begin + literal.len();
+                ranges.push(begin..start);
+            }
+            None => break,
+        }
+    }
+    ranges
+}
+
+// Splits a glob pattern into its runs of literal characters.
+fn pattern_literals(pattern: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut current = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' => literals.push(std::mem::take(&mut current)),
+            '[' => {
+                literals.push(std::mem::take(&mut current));
+                // A class can't be empty, so a leading ']' (after an optional '!') is part of it
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    literals.push(current);
+    literals.retain(|literal| !literal.is_empty());
+    literals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_ranges() {
+        let ranges = |pattern, name| match_ranges(&Pattern::new(pattern).unwrap(), name);
+        assert_eq!(ranges("*.rs", "main.rs"), vec![4..7]);
+        assert_eq!(ranges("ma*n?rs", "main.rs"), vec![0..2, 3..4, 5..7]);
+        assert_eq!(ranges("[mn]ain*", "main.rs"), vec![1..4]);
+        assert_eq!(ranges("*.rs", "main.py"), Vec::<Range<usize>>::new());
+        // The literal is the one at the end, not its first occurrence
+        assert_eq!(ranges("*na", "nana"), vec![2..4]);
+        assert_eq!(ranges("na*", "nana"), vec![0..2]);
+        assert_eq!(ranges("*an*", "nana"), vec![1..3]);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1.5k"), Ok(1536));
+        assert_eq!(parse_size("1GB"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_limit() {
+        let now = Local::now();
+        let ago = |s| now - parse_time_limit(s).unwrap().resolve(now);
+        assert_eq!(ago("2d").num_hours(), 48);
+        assert_eq!(ago("6mo").num_days(), 180);
+        assert_eq!(ago("90m").num_minutes(), 90);
+
+        let date = parse_time_limit("2024-03-01").unwrap().resolve(now);
+        assert_eq!(date.format("%Y-%m-%d %H:%M").to_string(), "2024-03-01 00:00");
+        let time = parse_time_limit("2024-03-01 14:30").unwrap().resolve(now);
+        assert_eq!(time.format("%H:%M").to_string(), "14:30");
+
+        assert!(parse_time_limit("2x").is_err());
+        assert!(parse_time_limit("yesterday").is_err());
+        assert!(parse_time_limit("999999999999d").is_err());
+        assert!(parse_time_limit("99999999999999999y").is_err());
+        // Representable durations reaching before any date stop at the earliest one
+        let earliest = parse_time_limit("100000000y").unwrap().resolve(now);
+        assert_eq!(earliest, DateTime::<Utc>::MIN_UTC);
+    }
+
+    #[test]
+    fn test_parse_reference_time() {
+        let dir = crate::tests::TempDir::new("reference");
+        let path = dir.join("file");
+        fs::write(&path, "").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(parse_reference_time(path), Ok(DateTime::<Local>::from(modified)));
+        fs::remove_file(path).unwrap();
+        assert!(parse_reference_time(path).unwrap_err().starts_with("could not read"));
+    }
+
+    #[test]
+    fn test_period() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        // A Thursday
+        let thursday = date("2024-02-29");
+        assert_eq!(Period::Day.first_day(thursday), thursday);
+        assert_eq!(Period::Week.first_day(thursday), date("2024-02-26"));
+        assert_eq!(Period::Week.first_day(date("2024-02-26")), date("2024-02-26"));
+        assert_eq!(Period::Month.first_day(thursday), date("2024-02-01"));
+
+        let start = Period::Day.start(Local::now()).unwrap();
+        assert_eq!(start.date_naive(), Local::now().date_naive());
+        assert_eq!(start.format("%H:%M").to_string(), "00:00");
+    }
+
+    #[test]
+    fn test_parse_owner() {
+        assert_eq!(parse_user("root"), Ok(0));
+        assert_eq!(parse_user("1234"), Ok(1234));
+        assert_eq!(parse_group("0"), Ok(0));
+        assert!(parse_user("no-such-user-here").is_err());
+    }
+
+    #[test]
+    fn test_entry_kind() {
+        let script = RawInfo {
+            is_executable: true,
+            ..crate::tests::mock_raw_info("/tmp/run.sh", 0, false)
+        };
+        let link = RawInfo {
+            is_symlink: true,
+            is_executable: true,
+            ..crate::tests::mock_raw_info("/tmp/run", 0, false)
+        };
+
+        assert!(EntryKind::File.matches(&script));
+        assert!(EntryKind::Executable.matches(&script));
+        assert!(EntryKind::Symlink.matches(&link));
+        assert!(!EntryKind::Executable.matches(&link));
+        assert!(!EntryKind::File.matches(&link));
+    }
+}
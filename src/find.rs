@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use glob::Pattern;
+
+use crate::filters::match_ranges;
+use crate::{list_directory, print_listing, DisplayArgs, ProcessedInfo, Settings};
+
+#[derive(clap::Args)]
+pub struct FindArgs {
+    /// Glob pattern matched against file names (e.g. "*.rs")
+    pattern: String,
+
+    /// Directories to search (default: current directory)
+    #[arg(default_value = ".")]
+    paths: Vec<String>,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+pub fn run(args: &FindArgs, settings: &Settings) -> i32 {
+    let pattern = match Pattern::new(&args.pattern) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("Error: invalid pattern {}: {}", args.pattern, e);
+            return 1;
+        }
+    };
+
+    let mut matches = Vec::new();
+    for root in &args.paths {
+        let root = Path::new(root);
+        if !root.is_dir() {
+            eprintln!("Error: {} is not a directory", root.display());
+            return 1;
+        }
+        search(root, root, &pattern, settings, &mut matches);
+    }
+
+    print_listing(matches, settings)
+}
+
+// Walks the directory in listing order, keeping the entries whose names match.
+fn search(
+    root: &Path,
+    directory: &Path,
+    pattern: &Pattern,
+    settings: &Settings,
+    matches: &mut Vec<ProcessedInfo>,
+) {
+    let mut raw_infos = list_directory(directory, &settings.scan);
+
+    // Matches are labeled with their path from the search root
+    for raw_info in raw_infos.iter_mut() {
+        let relative_path = raw_info.path.strip_prefix(root).unwrap_or(&raw_info.path);
+        raw_info.label = Some(relative_path.display().to_string());
+    }
+
+    for mut pinfo in settings.process_sorted(raw_infos) {
+        let name = pinfo
+            .rinfo
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_match = pattern.matches(&name);
+        pinfo.match_ranges = match_ranges(pattern, &name);
+        let subdir = if pinfo.rinfo.is_directory && !pinfo.rinfo.is_symlink {
+            Some(pinfo.rinfo.path.clone())
+        } else {
+            None
+        };
+
+        if is_match && settings.keep(&pinfo.rinfo) {
+            matches.push(pinfo);
+        }
+        if let Some(subdir) = subdir {
+            search(root, &subdir, pattern, settings, matches);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+
+use clap::ValueEnum;
+use log::debug;
+
+/// State of a path according to `git status`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, ValueEnum)]
+pub enum GitState {
+    /// Changed in the working tree but not staged
+    Modified,
+    /// Not tracked by git
+    Untracked,
+    /// Changes added to the index
+    Staged,
+    /// Ignored by .gitignore and friends
+    Ignored,
+}
+
+/// States reported by `git status` for a whole repository, keyed by absolute path.
+/// Directories also get the states of the files inside them.
+struct RepoStatus {
+    states: HashMap<PathBuf, HashSet<GitState>>,
+    /// Paths ignored themselves, not through their contents
+    ignored: HashSet<PathBuf>,
+}
+
+impl RepoStatus {
+    fn load(toplevel: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(toplevel)
+            .args(["status", "--porcelain=v1", "-z", "--ignored"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(Self::parse(
+            toplevel,
+            &String::from_utf8_lossy(&output.stdout),
+        ))
+    }
+
+    // Porcelain v1 with -z: "XY path\0", renames and copies followed by "origin\0".
+    fn parse(toplevel: &Path, porcelain: &str) -> Self {
+        let mut states: HashMap<PathBuf, HashSet<GitState>> = HashMap::new();
+        let mut ignored = HashSet::new();
+        let mut records = porcelain.split('\0');
+
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+            let (code, path) = record.split_at(3);
+            let mut code = code.chars();
+            let (x, y) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+            if x == 'R' || x == 'C' {
+                records.next();
+            }
+
+            let entry_states: Vec<GitState> = match (x, y) {
+                ('?', '?') => vec![GitState::Untracked],
+                ('!', '!') => vec![GitState::Ignored],
+                _ => {
+                    let mut entry_states = Vec::new();
+                    if "MADRCTU".contains(x) {
+                        entry_states.push(GitState::Staged);
+                    }
+                    if "MDTU".contains(y) {
+                        entry_states.push(GitState::Modified);
+                    }
+                    entry_states
+                }
+            };
+
+            // Propagate to the parent directories, up to the repository root
+            let path = toplevel.join(path.trim_end_matches('/'));
+            if (x, y) == ('!', '!') {
+                ignored.insert(path.clone());
+            }
+            for ancestor in path
+                .ancestors()
+                .take_while(|a| a.starts_with(toplevel) && *a != toplevel)
+            {
+                states
+                    .entry(ancestor.to_path_buf())
+                    .or_default()
+                    .extend(&entry_states);
+            }
+        }
+
+        RepoStatus { states, ignored }
+    }
+
+    fn states(&self, path: &Path) -> HashSet<GitState> {
+        let mut states = self.states.get(path).cloned().unwrap_or_default();
+
+        // Everything inside an untracked or ignored directory is untracked or ignored as well
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(ancestor_states) = self.states.get(ancestor) {
+                states.extend(
+                    ancestor_states
+                        .iter()
+                        .filter(|s| matches!(s, GitState::Untracked | GitState::Ignored)),
+                );
+            }
+        }
+
+        states
+    }
+
+    // Whether the path or a directory holding it is ignored.
+    fn is_ignored(&self, path: &Path) -> bool {
+        path.ancestors().any(|ancestor| self.ignored.contains(ancestor))
+    }
+}
+
+/// Lazily runs `git status` once per repository the listed entries belong to.
+#[derive(Default)]
+pub struct GitStatusCache {
+    toplevels: RefCell<HashMap<PathBuf, Option<PathBuf>>>,
+    repos: RefCell<HashMap<PathBuf, Option<Rc<RepoStatus>>>>,
+}
+
+impl GitStatusCache {
+    /// Git states of the given path. Empty if it isn't inside a git repository.
+    pub fn states(&self, path: &Path) -> HashSet<GitState> {
+        match self.find(path) {
+            Some((repo, path)) => repo.states(&path),
+            None => HashSet::new(),
+        }
+    }
+
+    /// Whether the path is ignored by git (e.g. by .gitignore or .git/info/exclude).
+    /// Unlike its Ignored state, directories aren't ignored for holding ignored files.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.find(path).is_some_and(|(repo, path)| repo.is_ignored(&path))
+    }
+
+    // Repository of the path, if any, and the absolute path as git status names it.
+    fn find(&self, path: &Path) -> Option<(Rc<RepoStatus>, PathBuf)> {
+        // The parent is resolved, but not the entry itself (it may be a symlink)
+        let (parent, name) = (path.parent()?, path.file_name()?);
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let parent = parent.canonicalize().ok()?;
+
+        let repo = self.repo_status(&parent)?;
+        Some((repo, parent.join(name)))
+    }
+
+    fn repo_status(&self, directory: &Path) -> Option<Rc<RepoStatus>> {
+        let toplevel = self
+            .toplevels
+            .borrow_mut()
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| find_toplevel(directory))
+            .clone()?;
+
+        self.repos
+            .borrow_mut()
+            .entry(toplevel.clone())
+            .or_insert_with(|| {
+                debug!("Reading git status of {}", toplevel.display());
+                RepoStatus::load(&toplevel).map(Rc::new)
+            })
+            .clone()
+    }
+}
+
+fn find_toplevel(directory: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let porcelain = " M src/main.rs\0A  new.rs\0R  renamed.rs\0old.rs\0?? notes/\0!! target/\0";
+        let repo = RepoStatus::parse(Path::new("/repo"), porcelain);
+        let states = |path: &str| repo.states(Path::new(path));
+
+        assert_eq!(
+            states("/repo/src/main.rs"),
+            HashSet::from([GitState::Modified])
+        );
+        assert_eq!(states("/repo/src"), HashSet::from([GitState::Modified]));
+        assert_eq!(states("/repo/new.rs"), HashSet::from([GitState::Staged]));
+        assert_eq!(
+            states("/repo/renamed.rs"),
+            HashSet::from([GitState::Staged])
+        );
+        assert!(states("/repo/old.rs").is_empty());
+        assert_eq!(
+            states("/repo/notes/todo.txt"),
+            HashSet::from([GitState::Untracked])
+        );
+        assert_eq!(
+            states("/repo/target/debug"),
+            HashSet::from([GitState::Ignored])
+        );
+        assert!(states("/repo/Cargo.toml").is_empty());
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let porcelain = "!! target/\0!! src/main.rs.orig\0 M src/main.rs\0";
+        let repo = RepoStatus::parse(Path::new("/repo"), porcelain);
+        let is_ignored = |path: &str| repo.is_ignored(Path::new(path));
+
+        assert!(is_ignored("/repo/target"));
+        assert!(is_ignored("/repo/target/debug/myls"));
+        assert!(is_ignored("/repo/src/main.rs.orig"));
+        // Holding an ignored file doesn't make a directory ignored
+        assert!(!is_ignored("/repo/src"));
+        assert!(!is_ignored("/repo/src/main.rs"));
+        assert!(!is_ignored("/repo"));
+    }
+}
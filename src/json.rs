@@ -0,0 +1,194 @@
+use std::io::{self, BufWriter, Write};
+
+use serde::Serialize;
+
+use crate::diagnostics::{self, EntryError};
+use crate::{ProcessedInfo, TimeField};
+
+/// Machine readable version of a listed entry.
+#[derive(Serialize)]
+struct JsonEntry {
+    name: String,
+    path: String,
+    kind: &'static str,
+    /// Bytes. Absent for symlinks and directories without a computed size.
+    size: Option<u64>,
+    /// Files and subdirectories directly inside a directory, with --counts
+    files: Option<u64>,
+    dirs: Option<u64>,
+    permissions: String,
+    owner: String,
+    group: String,
+    modified: String,
+    target: Option<String>,
+    executable: bool,
+    /// The metadata as read, for tools needing more than the formatted fields above:
+    /// st_mode with the type and special bits, device numbers, and times in seconds since
+    /// the epoch (absent where unknown)
+    mode: u32,
+    dev: u64,
+    rdev: u64,
+    nlink: u64,
+    mtime: Option<i64>,
+    atime: Option<i64>,
+    ctime: Option<i64>,
+}
+
+impl JsonEntry {
+    fn new(pinfo: &ProcessedInfo) -> Self {
+        let rinfo = &pinfo.rinfo;
+
+        let kind = if rinfo.vanished {
+            "vanished"
+        } else if rinfo.is_symlink {
+            "symlink"
+        } else if rinfo.is_directory {
+            "directory"
+        } else {
+            "file"
+        };
+
+        let size = if rinfo.is_directory {
+            rinfo.dir_size
+        } else if rinfo.is_symlink {
+            None
+        } else {
+            Some(rinfo.size)
+        };
+
+        let name = rinfo.label.clone().unwrap_or_else(|| {
+            rinfo
+                .path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "/".to_string())
+        });
+
+        JsonEntry {
+            name,
+            path: rinfo.path.display().to_string(),
+            kind,
+            size,
+            files: rinfo.entry_counts.map(|counts| counts.files),
+            dirs: rinfo.entry_counts.map(|counts| counts.dirs),
+            permissions: pinfo.permissions.clone(),
+            owner: pinfo.username.clone(),
+            group: pinfo.groupname.clone(),
+            modified: rinfo.modified_time.to_rfc3339(),
+            target: rinfo
+                .path
+                .read_link()
+                .ok()
+                .filter(|_| rinfo.is_symlink)
+                .map(|t| t.display().to_string()),
+            executable: pinfo.is_executable,
+            mode: rinfo.mode,
+            dev: rinfo.dev,
+            rdev: rinfo.rdev,
+            nlink: rinfo.links,
+            mtime: rinfo.time(TimeField::Mtime).map(|t| t.timestamp()),
+            atime: rinfo.time(TimeField::Atime).map(|t| t.timestamp()),
+            ctime: rinfo.time(TimeField::Ctime).map(|t| t.timestamp()),
+        }
+    }
+}
+
+/// Names of the fields of an entry, as columns of a tabular export (CSV/TSV).
+pub const FIELDS: [&str; 19] = [
+    "name",
+    "path",
+    "kind",
+    "size",
+    "files",
+    "dirs",
+    "permissions",
+    "owner",
+    "group",
+    "modified",
+    "target",
+    "executable",
+    "mode",
+    "dev",
+    "rdev",
+    "nlink",
+    "mtime",
+    "atime",
+    "ctime",
+];
+
+/// The fields of each entry as text, in the order of `FIELDS`. Absent values are left empty.
+pub fn rows(processed_infos: &[ProcessedInfo]) -> Vec<Vec<String>> {
+    let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let time = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_default();
+    processed_infos
+        .iter()
+        .map(|pinfo| {
+            let entry = JsonEntry::new(pinfo);
+            vec![
+                entry.name,
+                entry.path,
+                entry.kind.to_string(),
+                optional(entry.size),
+                optional(entry.files),
+                optional(entry.dirs),
+                entry.permissions,
+                entry.owner,
+                entry.group,
+                entry.modified,
+                entry.target.unwrap_or_default(),
+                entry.executable.to_string(),
+                entry.mode.to_string(),
+                entry.dev.to_string(),
+                entry.rdev.to_string(),
+                entry.nlink.to_string(),
+                time(entry.mtime),
+                time(entry.atime),
+                time(entry.ctime),
+            ]
+        })
+        .collect()
+}
+
+/// Every object in the output stream is tagged with its type, so errors can be told apart.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record {
+    Entry(Box<JsonEntry>),
+    Error(EntryError),
+}
+
+/// Prints the entries followed by any errors found while scanning them,
+/// either as a single JSON array or as one JSON object per line (NDJSON).
+pub fn print(processed_infos: &[ProcessedInfo], ndjson: bool) -> io::Result<()> {
+    let records: Vec<Record> = processed_infos
+        .iter()
+        .map(|pinfo| Record::Entry(Box::new(JsonEntry::new(pinfo))))
+        .chain(diagnostics::take().into_iter().map(Record::Error))
+        .collect();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if ndjson {
+        for record in &records {
+            writeln!(out, "{}", serde_json::to_string(record).unwrap())?;
+        }
+    } else {
+        writeln!(out, "{}", serde_json::to_string_pretty(&records).unwrap())?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::path::Path;
+
+    #[test]
+    fn test_error_record() {
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        let record = Record::Error(EntryError::new(Path::new("/tmp/gone"), "Error accessing", &error));
+        let json = serde_json::to_string(&record).unwrap();
+
+        assert!(json.starts_with(r#"{"type":"error","path":"/tmp/gone","kind":"not_found""#));
+    }
+}
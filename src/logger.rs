@@ -0,0 +1,60 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Minimal logger writing diagnostics to stderr, enabled by `-v` (info), `-vv` (debug) and
+/// `-vvv` (trace). Without it no logger is installed, so log calls cost next to nothing.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+// Messages are dimmed on a terminal, to stand apart from the listing
+static DIM: AtomicBool = AtomicBool::new(false);
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let label = match record.level() {
+                Level::Error => "error",
+                Level::Warn => "warn",
+                Level::Info => "info",
+                Level::Debug => "debug",
+                Level::Trace => "trace",
+            };
+            if DIM.load(Ordering::Relaxed) {
+                eprintln!("\x1b[2m[{}] {}\x1b[0m", label, record.args());
+            } else {
+                eprintln!("[{}] {}", label, record.args());
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init(verbosity: u8) {
+    let level = match verbosity {
+        0 => return,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    // Until the options are read, as colors are with --color auto
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    set_color(!no_color);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Dims messages if colors are enabled (e.g. not with --color never) and stderr is a terminal.
+pub fn set_color(color: bool) {
+    DIM.store(color && io::stderr().is_terminal(), Ordering::Relaxed);
+}
@@ -1,546 +1,3365 @@
+use std::cmp::Ordering;
 use std::env;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::Range;
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::MetadataExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::process;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, Instant};
 
-use chrono::{DateTime, Local};
-use clap::Parser;
+mod audit;
+mod bench;
+mod cache;
+mod category;
+mod cert;
+mod color;
+mod config;
+mod csv;
+mod diagnostics;
+mod diff;
+mod doctor;
+mod du;
+mod exec;
+mod filesystem;
+mod filters;
+mod find;
+mod git;
+mod json;
+mod logger;
+mod overlay;
+mod owners;
+mod recent;
+mod rename;
+mod report;
+mod sftp;
+mod source;
+mod stats;
+mod storage;
+mod template;
+mod theme;
+mod tree;
+mod view;
+mod xattr;
+
+use chrono::{DateTime, Local, TimeZone};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use glob::Pattern;
+use color::Palette;
+use config::Config;
+use diagnostics::EntryError;
+use filesystem::Mounts;
+use filters::{FilterArgs, Filters};
+use overlay::{Layer, OverlayMounts};
+use source::FileSource;
+use template::Template;
+use theme::{Theme, ThemeName};
+use log::{debug, info, trace};
+use serde::Deserialize;
+use unicode_width::UnicodeWidthChar;
 use users::{get_group_by_gid, get_user_by_uid};
 
 // COLORS: https://encrypted-tbn0.gstatic.com/images?q=tbn:ANd9GcT75fjCYt2l_dPGNNJcUj-nCjMSEgaCK1blGJcNR83oz8k47qFsWgF1Hw&s=10
 
-const DATE_COLOR_TODAY: &str = "\x1b[37m";
-const DATE_COLOR_1DAY: &str = "\x1b[38;5;39m";
-const DATE_COLOR_1MONTH: &str = "\x1b[38;5;33m";
 const HEADER_BACKGROUND: &str = "\x1b[4m\x1b[47m\x1b[30m"; // UNDERLINE, BLACK ON WHITE
 const COLOR_RESET: &str = "\x1b[0m";
 
+/// Exit code for --fail-if-empty when there's nothing to list
+const EXIT_EMPTY: i32 = 3;
+
 #[derive(Parser)]
 #[command(
     name = "myls",
     about = "Custom ls -l alternative with enhanced formatting",
-    long_about = "Custom ls -l alternative with enhanced formatting and customization.\nDisplays file information with zebra striping and colors."
+    long_about = "Custom ls -l alternative with enhanced formatting and customization.\nDisplays file information with zebra striping and colors.",
+    args_conflicts_with_subcommands = true
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    list: ListArgs,
+
+    /// Display the version number
+    #[arg(short = 'V', long)]
+    version: bool,
+
+    /// Log diagnostics to stderr (config files, colors, skipped entries, timings).
+    /// Repeat for more detail (-vv, -vvv).
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Don't print warnings or errors about individual entries (e.g. permission denied)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List files and directories (default when no subcommand is given)
+    List(ListArgs),
+    /// Show a directory and its subdirectories as a tree
+    Tree(tree::TreeArgs),
+    /// List entries with the total size of directories, biggest first
+    Du(du::DuArgs),
+    /// Compare the entries of two directories
+    Diff(diff::DiffArgs),
+    /// Search directories recursively for names matching a pattern
+    Find(find::FindArgs),
+    /// List the most recently modified files under a directory, newest first
+    Recent(recent::RecentArgs),
+    /// Preview what a sed-like substitution would rename the entries of a directory to,
+    /// without renaming anything
+    RenamePreview(rename::RenamePreviewArgs),
+    /// Manage the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manage the directory size cache used by `du --cached`
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Check terminal capabilities (colors, emoji, fonts, locale) and suggest options
+    Doctor,
+    /// Time the scanning, processing and formatting of a directory's listing against `ls -l`
+    Bench(bench::BenchArgs),
+}
+
+#[derive(clap::Args)]
+struct ListArgs {
     /// Files or directories to list (default: current directory)
     #[arg(default_value = ".")]
     paths: Vec<String>,
 
-    /// Show hidden files (starting with .) when listing a directory
-    #[arg(short, long)]
-    all: bool,
+    /// List paths given several times (e.g. by overlapping globs) once per time,
+    /// instead of once
+    #[arg(long)]
+    keep_duplicates: bool,
+
+    /// List the contents of subdirectories too, named by their path from the listed directory
+    #[arg(short = 'R', long)]
+    recursive: bool,
+
+    /// Show the contents of the subdirectory with the given name below its row, one level deep,
+    /// leaving the others collapsed. Can be repeated
+    #[arg(long, value_name = "NAME", conflicts_with = "recursive")]
+    expand: Vec<String>,
+
+    /// Summarize the listing into one row per owner, with their number of files and total size
+    #[arg(long, conflicts_with = "perm_summary")]
+    by_owner: bool,
+
+    /// Summarize the listing into the number of entries per permission mode,
+    /// warning about world-writable entries and writable entries in private directories
+    #[arg(long)]
+    perm_summary: bool,
 
+    /// Summarize the listing into one row per extension, with its number of files, total size
+    /// and largest file, the extensions taking the most space first
+    #[arg(long, conflicts_with_all = ["by_owner", "perm_summary"])]
+    stats: bool,
+
+    /// Run a command on each listed entry after printing, e.g. 'gzip -k {}'. "{}" stands for
+    /// the entry's path (appended if missing). Quotes are split as in a shell, but names are
+    /// passed as they are, without going through one
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["by_owner", "perm_summary", "stats"])]
+    exec: Option<String>,
+
+    /// Don't print the line summing up the table (e.g. 12 files, 3 dirs, 148.3M)
+    #[arg(long)]
+    no_summary: bool,
+
+    /// Like --exec, but run the command once with every path, as "{}" or at the end
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with_all = ["exec", "by_owner", "perm_summary", "stats"]
+    )]
+    exec_batch: Option<String>,
+
+    /// Run the command of --exec or --exec-batch without asking first. Without a terminal to
+    /// ask on, it isn't run otherwise
+    #[arg(long)]
+    yes: bool,
+
+    #[command(flatten)]
+    display: DisplayArgs,
+}
+
+/// Formatting options shared by every subcommand that prints a listing.
+#[derive(clap::Args)]
+struct DisplayArgs {
     /// Maximum length of file name to display. If 0 (default), no limit is applied.
     #[arg(long, default_value = "0")]
     max_name_length: usize,
 
+    /// Which part of names longer than --max-name-length is cut out
+    #[arg(long, value_enum, default_value_t = Truncate::Middle)]
+    truncate: Truncate,
+
+    /// Marker replacing the part of names cut out by --max-name-length
+    #[arg(long, default_value = "…")]
+    ellipsis: String,
+
     /// Color files based on their suffix, in the format "suffix=color", separated by commas.
     /// Example: --file-colors ".py=38;5;220m,.html=38;5;208m"
     #[arg(long, value_parser = parse_file_colors)]
     file_colors: Option<HashMap<String, String>>,
 
-    /// Shows folder icons
+    /// Shows folder icons (can also be enabled with `icons = true` in the config file)
     #[arg(short, long)]
     icons: bool,
 
-    /// Display the version number
+    /// Kinds of entries shown without any icon or placeholder, separated by commas
+    /// [default: taken from `no_icons_for` in the config file]
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    no_icons_for: Option<Vec<IconKind>>,
+
+    /// Glyph before directory names when icons are off, or "" for none
+    /// [default: taken from `icon_placeholder` in the config file, or ■]
+    #[arg(long, value_name = "GLYPH")]
+    icon_placeholder: Option<String>,
+
+    /// Key to sort entries by, after the listed directory and then subdirectories.
+    /// Ties are sorted by name, then by path and inode, so listings always come out the same.
+    #[arg(long, value_enum, default_value_t = SortBy::Name)]
+    sort: SortBy,
+
+    /// Timestamp shown in the modified column and used by --sort time, as with `ls --time`
+    #[arg(long, value_enum, default_value_t = TimeField::Mtime)]
+    time: TimeField,
+
+    /// Reverse the sort order. The listed directory and the grouping of directories are kept.
     #[arg(short, long)]
-    version: bool
-}
+    reverse: bool,
 
-fn parse_file_colors(s: &str) -> Result<HashMap<String, String>, String> {
-    let mut map = HashMap::new();
-    for kv in s.split(',') {
-        let parts: Vec<&str> = kv.split('=').collect();
-        if parts.len() != 2 {
-            return Err(format!("Invalid format: {}", kv));
-        }
-        map.insert(parts[0].to_string(), parts[1].to_string());
-    }
-    Ok(map)
-}
+    /// Where directories (and links to directories) are placed relative to files
+    #[arg(long, value_enum, default_value_t = GroupDirs::First)]
+    group_dirs: GroupDirs,
 
-fn main() {
-    let exit_code = run();
-    process::exit(exit_code);
-}
+    /// Whether sorting by name distinguishes upper and lower case
+    #[arg(long, value_enum, default_value_t = SortCase::Insensitive)]
+    sort_case: SortCase,
 
-fn run() -> i32 {
-    let args = Args::parse();
+    /// Sort names by their raw characters, as ls does in the C locale (same as --sort-case sensitive)
+    #[arg(long, conflicts_with = "sort_case")]
+    case_sensitive_sort: bool,
 
-    if args.version {
-        println!("myls {}", env!("CARGO_PKG_VERSION"));
-        return 0;
-    }
+    /// Where entries starting with a dot are placed when sorting by name
+    #[arg(long, value_enum, default_value_t = SortDotfiles::First)]
+    sort_dotfiles: SortDotfiles,
 
-    let paths: Vec<PathBuf> = if args.paths.len() == 1 && args.paths[0] == "." {
-        vec![env::current_dir().unwrap_or_else(|_| PathBuf::from("."))]
-    } else {
-        args.paths.iter().map(|p| PathBuf::from(p)).collect()
-    };
+    /// Columns to keep when the terminal is too narrow, from most to least important.
+    /// Columns at the end of the list are hidden first. The name is never hidden.
+    /// [default: name,size,modified,perm,owner, or `column_priority` from the config file]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    column_priority: Option<Vec<Column>>,
 
-    let paths: Vec<&Path> = paths.iter().map(|p| p.as_path()).collect();
+    /// Columns to show (e.g. perm,size,name), in their usual order. Listing links, created,
+    /// accessed, cert, layer or xattrs turns them on as their own option would;
+    /// expires needs --expires.
+    /// [default: every column turned on]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    columns: Option<Vec<Column>>,
 
-    let mut raw_infos: Vec<RawInfo> = Vec::new();
+    /// Layout of each row, with a placeholder per column, optionally padded:
+    /// e.g. "{perm} {size:>8} {name}". Replaces the table and its header
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = Template::parse,
+        conflicts_with = "columns"
+    )]
+    format: Option<Template>,
 
-    for path in &paths {
-        if !path.exists() {
-            eprintln!("Error: {} does not exist", path.display());
-            return 1;
-        }
+    /// Apply the options of a view defined in the config file (e.g. `[view.media]` with
+    /// `columns = ["size", "name"]`, `type = "image,video"` and `sort = "size"`).
+    /// Options given on the command line win over the view's
+    #[arg(long, value_name = "NAME")]
+    view: Option<String>,
 
-        // Single dir mode: list dir contents, after dir info itself
-        if path.is_dir() && paths.len() == 1 {
-            if let Some(mut main_dir_info) = get_file_info(path) {
-                main_dir_info.is_main_dir = true;
-                raw_infos.push(main_dir_info);
-            }
-            raw_infos.extend(list_directory(path, args.all));
-        }
-        // Normal mode: list details of given files and dirs
-        else {
-            if let Some(file_info) = get_file_info(path) {
-                raw_infos.push(file_info);
-            }
-        }
-    }
+    /// Show the owner's full name (GECOS field) instead of the login name, when available
+    #[arg(long)]
+    owner_full_name: bool,
 
-    // Process the raw data into information needed for printing
-    let mut processed_infos: Vec<ProcessedInfo> = raw_infos
-        .into_iter()
-        .map(|raw_info| ProcessedInfo::new(raw_info, args.icons, args.max_name_length))
-        .collect();
+    /// Show owners and groups as numeric ids (e.g. 1000:1000) instead of names, without looking
+    /// them up (faster with slow user databases, e.g. LDAP)
+    #[arg(short = 'n', long, conflicts_with = "owner_full_name")]
+    numeric_ids: bool,
 
-    // Sort: main dir first, then directories (and links to directories), then by name
-    processed_infos.sort_by(|a, b| {
-        a.sort_keys.cmp(&b.sort_keys)
-    });
+    /// Leave the owner column out when every entry has the same owner and group, naming them
+    /// once next to the header instead (e.g. "all owned by alice:alice")
+    #[arg(long)]
+    elide_owner: bool,
 
-    let max_owner_colsize = processed_infos
-        .iter()
-        .map(|pi| pi.username.len() + pi.groupname.len())
-        .max()
-        .unwrap_or(0)
-        + 1;
+    /// Show the size of the file a link points to (marked with →) instead of leaving it blank
+    #[arg(long)]
+    link_size: bool,
 
-    // Adds padding and colors to the output.
-    let mut displayable_infos: Vec<DisplayableInfo> = processed_infos
-        .into_iter()
-        .enumerate()
-        .map(|(i, pinfo)| {
-            DisplayableInfo::new(
-                i,
-                pinfo,
-                max_owner_colsize,
-                args.file_colors.as_ref().unwrap_or(&HashMap::new()),
-            )
-        })
-        .collect();
+    /// Layout of sizes: "aligned" lines up decimal points and units across rows
+    #[arg(long, value_enum, default_value_t = SizeStyle::Compact)]
+    size_style: SizeStyle,
 
-    // Print header with inverted colors for more contrast
-    let header = format!(
-        "{:>4} {:>7} {:>width$} {:>10} NAME",
-        "PERM",
-        "SIZE",
-        "OWNER",
-        "MODIFIED",
-        width = max_owner_colsize
-    );
-    println!("{}{}{}", HEADER_BACKGROUND, header, COLOR_RESET);
+    /// Layout of permissions: "symbolic" shows them as ls -l does (e.g. drwxr-xr-x), with the
+    /// type of entry and the setuid, setgid and sticky bits
+    #[arg(long, value_enum, default_value_t = PermStyle::Octal)]
+    perm_style: PermStyle,
 
-    // If the input is a single directory, print its own info before the content list
-    if !displayable_infos.is_empty() && displayable_infos[0].is_main_dir {
-        let main_dir_info = displayable_infos.remove(0);
-        println!(
-            "{} {} {} {} {}",
-            main_dir_info.permission_col,
-            main_dir_info.size_col,
-            main_dir_info.owner_col,
-            main_dir_info.date_col,
-            main_dir_info.name_col
-        );
-        if !displayable_infos.is_empty() {
-            println!("{}", "-".repeat(60));
-        }
-    }
+    /// Which permissions to highlight when they let others than the owner write
+    /// [default: taken from `writable_warning` in the config file, or "world"]
+    #[arg(long, value_enum)]
+    writable_warning: Option<WritableWarning>,
 
-    // Print each file with formatted output
-    for dinfo in displayable_infos {
-        println!(
-            "{} {} {} {} {}",
-            dinfo.permission_col, dinfo.size_col, dinfo.owner_col, dinfo.date_col, dinfo.name_col
-        );
-    }
+    /// Show how many days are left before each entry gets older than the given number of days,
+    /// as with retention policies (e.g. tmpwatch). Entries past the limit are shown in red.
+    #[arg(long, value_name = "DAYS")]
+    expires: Option<i64>,
 
-    0
-}
+    /// Show how many days are left before certificates (.pem, .crt, .cer, .der) expire,
+    /// in red when fewer than DAYS (--cert-expiry=DAYS, default: 30). Bundles show their first expiry,
+    /// and keys are marked as such
+    #[arg(
+        long,
+        value_name = "DAYS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "30"
+    )]
+    cert_expiry: Option<i64>,
 
-// #[derive(Debug)]
-struct RawInfo {
-    path: PathBuf,
-    permissions: u32,
-    size: u64,
-    owner_uid: u32,
-    group_gid: u32,
-    modified_time: DateTime<Local>,
-    is_directory: bool,
-    is_executable: bool,
-    is_symlink: bool,
-    is_main_dir: bool,
-}
+    /// Show whether entries on an overlay filesystem (e.g. inside a container) come from
+    /// the writable upper layer or from the read-only lower (image) layers
+    #[arg(long)]
+    layer: bool,
 
-struct ProcessedInfo {
-    rinfo: RawInfo,
-    permissions: String,
-    size: String,
-    size_unit: String,
-    username: String,
-    groupname: String,
-    name: String,
-    target_name: String,
-    is_executable: bool,
-    sort_keys: (u8, String),
-}
+    /// Show how many extended attributes each entry carries (blank for none),
+    /// to spot files with unexpected metadata (e.g. before archiving)
+    #[arg(long)]
+    xattrs: bool,
 
-impl ProcessedInfo {
-    const KB: u64 = 1024;
-    const MB: u64 = Self::KB * 1024;
-    const GB: u64 = Self::MB * 1024;
+    /// Show the number of hard links of each entry, as the second column of ls -l does
+    #[arg(long)]
+    links: bool,
 
-    fn new(raw_info: RawInfo, show_icons: bool, max_name_length: usize) -> Self {
-        // Format permissions as octal string.
-        let permissions = format!("{:03o}", raw_info.permissions);
+    /// When to use colors and other styling (zebra stripes, header): "auto" leaves them out when
+    /// the output isn't a terminal (e.g. piped to grep) or the NO_COLOR variable is set
+    #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+    color: ColorWhen,
 
-        let (size, size_unit) = Self::get_size_and_unit(&raw_info);
+    /// Extend a color to the whole row: "age" uses the modification date color
+    #[arg(long, value_enum, default_value_t = RowColor::None)]
+    row_color: RowColor,
 
-        let username = get_user_by_uid(raw_info.owner_uid)
-            .map(|u| u.name().to_string_lossy().to_string())
-            .unwrap_or_else(|| raw_info.owner_uid.to_string());
+    /// Color scheme, including presets for color blindness that also underline warnings
+    /// (e.g. huge sizes, future dates) [default: taken from the config file, or "default"]
+    #[arg(long, value_enum)]
+    theme: Option<ThemeName>,
 
-        let groupname = get_group_by_gid(raw_info.group_gid)
-            .map(|g| g.name().to_string_lossy().to_string())
-            .unwrap_or_else(|| raw_info.group_gid.to_string());
+    /// Order of day, month and year in dates [default: taken from the locale]
+    #[arg(long, value_enum)]
+    date_order: Option<DateOrder>,
 
-        let target = if raw_info.is_symlink {
-            raw_info.path.read_link().ok()
-        } else {
-            None
-        };
+    /// Format of modification dates, in strftime syntax (e.g. "%Y-%m-%d %H:%M"), used for every
+    /// date instead of the time for today's entries and the day or year for older ones
+    #[arg(long, value_name = "FORMAT", value_parser = parse_date_format)]
+    date_format: Option<String>,
 
-        let targets_folder = target
-            .as_ref()
-            .map(|t| t.exists() && t.is_dir())
-            .unwrap_or(false);
+    /// Sortable timestamps as in GNU ls, instead of the adaptive short dates
+    #[arg(long, value_enum, conflicts_with = "date_format")]
+    time_style: Option<TimeStyle>,
 
-        // Enshorten names if needed.
-        let base_name = raw_info
-            .path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/".to_string());
+    /// Show when entries were created, on filesystems recording it (shown as - elsewhere)
+    #[arg(long)]
+    show_created: bool,
 
-        let name = if max_name_length > 0 {
-            Self::pstr(&base_name, max_name_length)
-        } else {
-            base_name.to_string()
-        };
+    /// Show when entries were last accessed (depending on mount options, e.g. relatime)
+    #[arg(long)]
+    show_accessed: bool,
 
-        let target_name = if let Some(ref target) = target {
-            let target_str = target.display().to_string();
-            if max_name_length > 0 {
-                Self::pstr(&target_str, max_name_length)
-            } else {
-                target_str
-            }
-        } else {
-            String::new()
-        };
+    /// Show how long ago entries were modified (e.g. 3h ago, 2d ago, 5mo ago) instead of dates
+    #[arg(long, conflicts_with_all = ["date_format", "time_style"])]
+    relative_time: bool,
 
-        // Format names with folder emoji if directory.
-        let folder_icon = if !show_icons {
-            "■"
-        } else if raw_info.is_main_dir {
-            "📂"
-        } else {
-            "📁"
-        };
-        
-        let name = if raw_info.is_directory {
-            format!("{} {}", folder_icon, name)
-        } else {
-            name
-        };
+    /// Render as if the time were NOW (YYYY-MM-DD HH:MM[:SS], or @SECONDS since the epoch),
+    /// ignoring the locale and the terminal (colors only with --color always, no width limit),
+    /// for output that golden-file tests can compare
+    #[arg(long, value_name = "NOW", value_parser = parse_now)]
+    deterministic: Option<DateTime<Local>>,
 
-        let target_name = if !target_name.is_empty() && targets_folder {
-            format!("{} {}", folder_icon, target_name)
-        } else {
-            target_name
-        };
+    /// Emphasize rows whose names match the glob pattern, without hiding the others.
+    /// Can be repeated.
+    #[arg(long, value_name = "PATTERN")]
+    highlight: Vec<Pattern>,
 
-        // Disconsider directories and folder links as executables.
-        let is_executable = raw_info.is_executable
-            && !raw_info.is_directory
-            && (!target.is_some() || !targets_folder);
+    /// Size shown for directories: "shallow" sums the files directly inside them (marked with ≈),
+    /// "deep" sums everything below them, as the du subcommand does
+    #[arg(long, value_enum, default_value_t = DirSize::None)]
+    dir_size: DirSize,
 
-        let sort_name = raw_info
-            .path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_lowercase())
-            .unwrap_or_else(|| "/".to_string());
-        let sort_keys = if raw_info.is_main_dir {
-            (0, sort_name)
-        } else if raw_info.is_directory || targets_folder {
-            (1, sort_name)
-        } else {
-            (2, sort_name)
-        };
+    /// Same as --dir-size deep
+    #[arg(long, conflicts_with = "dir_size")]
+    dir_sizes: bool,
 
-        ProcessedInfo {
-            rinfo: raw_info,
-            permissions,
-            size,
-            size_unit,
-            username,
-            groupname,
-            name,
-            target_name,
-            is_executable,
-            sort_keys,
-        }
-    }
+    /// Seconds after which deep directory sizes stop being summed, those unfinished showing
+    /// what was found so far (marked with >). Without it, sizes are exact however long they take
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    dir_size_timeout: Option<u64>,
 
-    fn get_size_and_unit(raw_info: &RawInfo) -> (String, String) {
-        if raw_info.is_directory || raw_info.is_symlink {
-            return (String::new(), String::new());
-        }
+    /// Show how many files and subdirectories each directory contains (e.g. 12f/3d)
+    #[arg(long)]
+    counts: bool,
 
-        if raw_info.size < Self::KB {
-            (raw_info.size.to_string(), "B".to_string())
-        } else if raw_info.size < Self::MB {
-            ((raw_info.size / Self::KB).to_string(), "K".to_string())
-        } else if raw_info.size < Self::GB {
-            (format!("{:.1}", raw_info.size as f64 / Self::MB as f64), "M".to_string())
-        } else {
-            (format!("{:.1}", raw_info.size as f64 / Self::GB as f64), "G".to_string())
-        }
-    }
+    /// Show how many entries the targets of links to directories contain (e.g. 1,204 items)
+    #[arg(long)]
+    target_counts: bool,
 
-    fn pstr(string: &str, maxlength: usize) -> String {
-        if string.len() > maxlength + 5 {
-            let half_index = maxlength / 2;
-            format!(
-                "{}(...){}", 
-                &string[..half_index], 
-                &string[string.len() - half_index..]
-            )
-        } else {
-            string.to_string()
-        }
-    }
+    /// Show entries deleted while being listed as "(vanished)" rows, instead of skipping them
+    #[arg(long)]
+    show_vanished: bool,
+
+    /// Don't print the row of the listed directory itself
+    #[arg(long)]
+    no_self: bool,
+
+    /// Character of the line separating the listed directory from its contents
+    #[arg(long, default_value_t = '-')]
+    separator_char: char,
+
+    /// Width of the separator line, or "auto" to span the whole terminal
+    #[arg(long, default_value = "60", value_parser = parse_separator_width)]
+    separator_width: SeparatorWidth,
+
+    /// Color of the separator line, in the same format as --file-colors (e.g. "38;5;240m")
+    #[arg(long)]
+    separator_color: Option<String>,
+
+    /// Exit with code 3 when there are no entries to list (the listed directory itself aside)
+    #[arg(long)]
+    fail_if_empty: bool,
+
+    /// Print only the first N entries of the table, in the chosen order, followed by how many
+    /// were left out
+    #[arg(long, value_name = "N")]
+    max_entries: Option<usize>,
+
+    /// Print every entry, even with --max-entries (e.g. set by a view)
+    #[arg(long)]
+    all_entries: bool,
+
+    /// Output format: csv and tsv have a header row and leave errors on stderr
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        conflicts_with_all = ["json", "ndjson"]
+    )]
+    output: OutputFormat,
+
+    /// Print only the paths of the entries, each ended by a NUL byte, for `xargs -0`.
+    /// The listed directory itself is left out
+    #[arg(short = '0', long, conflicts_with_all = ["output", "json", "ndjson"])]
+    print0: bool,
+
+    /// Print entries as a JSON array, errors included as objects of type "error".
+    /// Same as --output json
+    #[arg(long, conflicts_with = "ndjson")]
+    json: bool,
+
+    /// Print entries as newline delimited JSON, one object per line. Same as --output ndjson
+    #[arg(long)]
+    ndjson: bool,
+
+    #[command(flatten, next_help_heading = "Filters")]
+    filter: FilterArgs,
 }
 
-struct DisplayableInfo {
-    permission_col: String,
-    size_col: String,
-    owner_col: String,
-    date_col: String,
-    name_col: String,
-    is_main_dir: bool,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum PermStyle {
+    /// Permission bits in octal (e.g. 755)
+    #[default]
+    Octal,
+    /// Type and permissions as ls -l shows them (e.g. drwxr-xr-x)
+    Symbolic,
 }
 
-impl DisplayableInfo {
-    // ANSI color codes for zebra striping
-    const ZEBRA_EVEN: &'static str = "\x1b[48;5;236m"; // Dark gray background
-    const ZEBRA_ODD: &'static str = "\x1b[48;5;235m";  // Slightly darker gray background
-    const GREEN: &'static str = "\x1b[32m";            // Green text for executables
-    const YELLOW: &'static str = "\x1b[33m";           // Yellow text for mega size
-    const RED: &'static str = "\x1b[31m";              // Red text for giga size
+/// Entries whose permissions stand out because they can be written by others than the owner.
+/// Symlinks (always 777) and sticky directories (e.g. /tmp) are writable by design.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum WritableWarning {
+    /// No highlight
+    None,
+    /// Entries anyone can write to
+    #[default]
+    World,
+    /// Entries the group or anyone can write to
+    Group,
+}
 
-    fn new(
-        row_index: usize,
-        processed_info: ProcessedInfo,
-        max_owner_colsize: usize,
-        file_colors: &HashMap<String, String>,
-    ) -> Self {
-        // Apply zebra striping
-        let reset_color = format!(
-            "{}{}",
-            COLOR_RESET,
-            if row_index % 2 == 0 {
-                Self::ZEBRA_EVEN
-            } else {
-                Self::ZEBRA_ODD
-            }
-        );
+impl WritableWarning {
+    /// The theme with the highlights left out painted as other permissions.
+    fn apply(self, mut theme: Theme) -> Theme {
+        if self != WritableWarning::Group {
+            theme.group_writable = theme.perm_group;
+        }
+        if self == WritableWarning::None {
+            theme.world_writable = theme.perm_other;
+        }
+        theme
+    }
+}
 
-        let permission_col = format!("{}{:>4}", reset_color, processed_info.permissions);
-        let size_col = Self::fmt_size(&processed_info, &reset_color);
-        let owner_col = format!(
-            "{:<width$}",
-            Self::fmt_owner(&processed_info),
-            width = max_owner_colsize
-        );
-        let date_col = Self::fmt_modified_time(&processed_info, &reset_color);
-        let name_col = format!(
-            "{}{}",
-            Self::fmt_name(&processed_info, file_colors),
-            COLOR_RESET
-        );
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorWhen {
+    /// Only when writing to a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+    Never,
+}
 
-        DisplayableInfo {
-            permission_col,
-            size_col,
-            owner_col,
-            date_col,
-            name_col,
-            is_main_dir: processed_info.rinfo.is_main_dir,
+impl ColorWhen {
+    fn enabled(self) -> bool {
+        match self {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && io::stdout().is_terminal()
+            }
         }
     }
+}
 
-    fn fmt_size(pinfo: &ProcessedInfo, reset_color: &str) -> String {
-        if pinfo.size.is_empty() {
-            return "      -".to_string();
-        }
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum RowColor {
+    /// Only the cells themselves are colored
+    #[default]
+    None,
+    /// Rows take the color of their modification date (today, this month, older)
+    Age,
+}
 
-        let unit_color = match pinfo.size_unit.as_str() {
-            "B" | "K" => Self::GREEN,
-            "M" => Self::YELLOW,
-            _ => Self::RED,
-        };
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum DateOrder {
+    /// 31/12/2024
+    #[default]
+    Dmy,
+    /// 12/31/2024
+    Mdy,
+    /// 2024-12-31
+    Ymd,
+}
 
-        format!(
-            "{:>6}{}{}{}",
-            pinfo.size, unit_color, pinfo.size_unit, reset_color
-        )
-    }
+impl DateOrder {
+    // Languages writing the year first, and countries putting the month before the day.
+    const YMD_LANGUAGES: [&'static str; 7] = ["ja", "zh", "ko", "hu", "lt", "mn", "sv"];
+    const MDY_TERRITORIES: [&'static str; 2] = ["US", "PH"];
 
-    fn fmt_owner(pinfo: &ProcessedInfo) -> String {
-        format!("{}:{}", pinfo.username, pinfo.groupname)
+    /// Usual order for the time locale (LC_ALL, then LC_TIME, then LANG).
+    fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::from_locale(&locale)
     }
 
-    fn fmt_modified_time(pinfo: &ProcessedInfo, reset_color: &str) -> String {
-        let now = Local::now();
-        let mdays = (now - pinfo.rinfo.modified_time).num_days();
+    fn from_locale(locale: &str) -> Self {
+        // e.g. "en_US.UTF-8": language "en", territory "US"
+        let name = locale.split(['.', '@']).next().unwrap_or("");
+        let (language, territory) = name.split_once('_').unwrap_or((name, ""));
 
-        let (color, fmt) = if mdays > 364 {
-            (DATE_COLOR_1MONTH, "%d/%m/%Y")
-        } else if mdays > 30 {
-            (DATE_COLOR_1MONTH, "%d/%m")
-        } else if mdays > 0 {
-            (DATE_COLOR_1DAY, "%d/%m")
+        if Self::YMD_LANGUAGES.contains(&language) {
+            DateOrder::Ymd
+        } else if Self::MDY_TERRITORIES.contains(&territory) {
+            DateOrder::Mdy
         } else {
-            (DATE_COLOR_TODAY, "%H:%M")
-        };
-
-        format!(
-            "{}{} {}",
-            color,
-            pinfo.rinfo.modified_time.format(fmt),
-            reset_color
-        )
+            DateOrder::Dmy
+        }
+    }
+
+    // Formats for dates older than a year, and for those within the year
+    fn formats(self) -> (&'static str, &'static str) {
+        match self {
+            DateOrder::Dmy => ("%d/%m/%Y", "%d/%m"),
+            DateOrder::Mdy => ("%m/%d/%Y", "%m/%d"),
+            DateOrder::Ymd => ("%Y-%m-%d", "%m-%d"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TimeStyle {
+    /// 2024-12-31 for dates older than a year, 12-31 23:59 for the others
+    Iso,
+    /// 2024-12-31 23:59
+    LongIso,
+    /// 2024-12-31 23:59:59.000000000 +0000
+    FullIso,
+}
+
+/// How modification dates are written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum DateStyle {
+    /// Day or year for older dates, and time for today's, in the given order
+    Adaptive(DateOrder),
+    /// GNU ls' iso style
+    Iso,
+    /// The same strftime format for every date
+    Custom(String),
+    /// Time elapsed since the date, in its largest unit
+    Relative,
+}
+
+impl DateStyle {
+    fn new(args: &DisplayArgs) -> Self {
+        if args.relative_time {
+            return DateStyle::Relative;
+        }
+        match (&args.date_format, args.time_style) {
+            (Some(format), _) => DateStyle::Custom(format.clone()),
+            (None, Some(TimeStyle::Iso)) => DateStyle::Iso,
+            (None, Some(TimeStyle::LongIso)) => DateStyle::Custom("%Y-%m-%d %H:%M".to_string()),
+            (None, Some(TimeStyle::FullIso)) => {
+                DateStyle::Custom("%Y-%m-%d %H:%M:%S%.9f %z".to_string())
+            }
+            (None, None) if args.deterministic.is_some() => {
+                DateStyle::Adaptive(args.date_order.unwrap_or_default())
+            }
+            (None, None) => {
+                DateStyle::Adaptive(args.date_order.unwrap_or_else(DateOrder::from_env))
+            }
+        }
+    }
+
+    // Formats for dates older than a year, for those within the year, and for today's
+    fn formats(&self) -> (&str, &str, &str) {
+        match self {
+            DateStyle::Adaptive(order) => {
+                let (year_fmt, day_fmt) = order.formats();
+                (year_fmt, day_fmt, "%H:%M")
+            }
+            DateStyle::Iso => ("%Y-%m-%d", "%m-%d %H:%M", "%m-%d %H:%M"),
+            DateStyle::Custom(format) => (format, format, format),
+            DateStyle::Relative => unreachable!("relative dates aren't formatted"),
+        }
+    }
+}
+
+impl Default for DateStyle {
+    fn default() -> Self {
+        DateStyle::Adaptive(DateOrder::default())
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum Truncate {
+    /// Keep the start and the end of names (e.g. prefixes and extensions)
+    #[default]
+    Middle,
+    /// Keep the start of names
+    End,
+    /// Keep the end of names
+    Start,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SeparatorWidth {
+    Auto,
+    Fixed(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the config file as a single compact string, to be imported elsewhere
+    Export,
+    /// Replace the config file with an exported string, given directly or read from a file or URL
+    Import {
+        /// Exported string, path to a file or http(s) URL containing it
+        source: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show where the cache is and how many directories it holds
+    Status,
+    /// Delete the cache
+    Clear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortCase {
+    /// Uppercase names sort before lowercase ones ("B" before "a")
+    Sensitive,
+    /// Names are compared ignoring case
+    Insensitive,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// Alphabetical order
+    Name,
+    /// Biggest first
+    Size,
+    /// Most recent first, by the timestamp chosen with --time
+    Time,
+    /// Alphabetical order of extensions, entries without one first
+    Ext,
+    /// Alphabetical order of owners
+    Owner,
+    /// Alphabetical order of groups
+    Group,
+    /// Entries nearest to the listed directory first, in recursive listings
+    Depth,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum TimeField {
+    /// Last modification of the contents
+    #[default]
+    #[value(alias = "modification")]
+    Mtime,
+    /// Last access
+    #[value(alias = "access", alias = "use")]
+    Atime,
+    /// Last change of the contents or the metadata (e.g. permissions, renames)
+    #[value(alias = "status")]
+    Ctime,
+    /// Creation, on filesystems recording it
+    #[value(alias = "creation")]
+    Birth,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DirSize {
+    /// Directories have no size
+    None,
+    /// Total size of the files directly inside
+    Shallow,
+    /// Total size of everything below
+    Deep,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum SizeStyle {
+    /// Units right after the number (e.g. 1.5M, 1023B)
+    #[default]
+    Compact,
+    /// Integer parts, decimals and units each in their own sub-column
+    Aligned,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GroupDirs {
+    /// Directories come before files
+    First,
+    /// Directories come after files
+    Last,
+    /// Directories are sorted along with files
+    None,
+}
+
+/// How entries are ordered: by group (directories and files), then within their group.
+#[derive(Clone, Copy)]
+struct SortOrder {
+    by: SortBy,
+    reverse: bool,
+    group_dirs: GroupDirs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortDotfiles {
+    /// Dotfiles come before the other entries
+    First,
+    /// Dotfiles are sorted as if they had no leading dot
+    Mixed,
+    /// Dotfiles come after the other entries
+    Last,
+}
+
+/// Entries that get an icon (or the placeholder) before their name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum IconKind {
+    /// Directories
+    Dir,
+    /// Targets of links to directories
+    Target,
+}
+
+/// How owners and groups are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OwnerNames {
+    /// Login and group names
+    Login,
+    /// Full name of the owner (GECOS field) when available, and group name
+    FullName,
+    /// Uid and gid, without looking up any name
+    Numeric,
+}
+
+impl OwnerNames {
+    fn new(args: &DisplayArgs) -> Self {
+        if args.numeric_ids {
+            OwnerNames::Numeric
+        } else if args.owner_full_name {
+            OwnerNames::FullName
+        } else {
+            OwnerNames::Login
+        }
+    }
+
+    /// Name of the owner, or its uid when it has none.
+    fn owner(self, uid: u32) -> String {
+        let full_name = match self {
+            OwnerNames::Numeric => return uid.to_string(),
+            OwnerNames::FullName => {
+                get_user_gecos(uid).and_then(|g| ProcessedInfo::gecos_full_name(&g))
+            }
+            OwnerNames::Login => None,
+        };
+        full_name.unwrap_or_else(|| {
+            get_user_by_uid(uid)
+                .map(|u| u.name().to_string_lossy().to_string())
+                .unwrap_or_else(|| uid.to_string())
+        })
+    }
+
+    /// Name of the group, or its gid when it has none.
+    fn group(self, gid: u32) -> String {
+        if self == OwnerNames::Numeric {
+            return gid.to_string();
+        }
+        get_group_by_gid(gid)
+            .map(|g| g.name().to_string_lossy().to_string())
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Glyphs put before names.
+struct Icons {
+    /// Folder emoji, with --icons
+    enabled: bool,
+    /// Kinds of entries left without any glyph
+    hidden: Vec<IconKind>,
+    /// Glyph when icons are off, none if empty
+    placeholder: String,
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Icons {
+            enabled: false,
+            hidden: Vec::new(),
+            placeholder: "■".to_string(),
+        }
+    }
+}
+
+impl Icons {
+    // The open folder stands for the listed directory
+    fn glyph(&self, kind: IconKind, is_main_dir: bool) -> Option<&str> {
+        if self.hidden.contains(&kind) {
+            None
+        } else if !self.enabled {
+            Some(self.placeholder.as_str()).filter(|glyph| !glyph.is_empty())
+        } else if is_main_dir {
+            Some("📂")
+        } else {
+            Some("📁")
+        }
+    }
+
+    fn prefix(&self, kind: IconKind, is_main_dir: bool, name: String) -> String {
+        match self.glyph(kind, is_main_dir) {
+            Some(glyph) => format!("{} {}", glyph, name),
+            None => name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Column {
+    Perm,
+    /// Number of hard links, with --links
+    Links,
+    Size,
+    Owner,
+    Modified,
+    /// Birth time of the entry, with --show-created
+    Created,
+    /// Last access time of the entry, with --show-accessed
+    Accessed,
+    /// Days left before the entry exceeds the --expires age
+    Expires,
+    /// Days left before a certificate expires, with --cert-expiry
+    Cert,
+    /// Overlay layer of the entry, with --layer
+    Layer,
+    /// Number of extended attributes, with --xattrs
+    Xattrs,
+    Name,
+}
+
+impl Column {
+    const DEFAULT_PRIORITY: [Column; 5] = [
+        Column::Name,
+        Column::Size,
+        Column::Modified,
+        Column::Perm,
+        Column::Owner,
+    ];
+
+    // Display order of the columns
+    const ALL: [Column; 12] = [
+        Column::Perm,
+        Column::Links,
+        Column::Size,
+        Column::Owner,
+        Column::Modified,
+        Column::Created,
+        Column::Accessed,
+        Column::Expires,
+        Column::Cert,
+        Column::Layer,
+        Column::Xattrs,
+        Column::Name,
+    ];
+
+    // Columns whose width depends on the options and the entries, aligned to their widest cell
+    const ALIGNED: [Column; 4] = [
+        Column::Perm,
+        Column::Modified,
+        Column::Created,
+        Column::Accessed,
+    ];
+
+    fn header(self, max_owner_colsize: usize, widths: &HashMap<Column, usize>) -> String {
+        // Dates are followed by a space
+        let date_header = |label: &str| {
+            let width = widths.get(&self).copied().unwrap_or(label.len() + 1);
+            format!("{:>width$} ", label, width = width - 1)
+        };
+        match self {
+            Column::Perm => {
+                let width = widths.get(&self).copied().unwrap_or(4);
+                format!("{:>width$}", "PERM", width = width)
+            }
+            Column::Links => format!("{:>5}", "LINKS"),
+            Column::Size => format!("{:>7}", "SIZE"),
+            Column::Owner => format!("{:>width$}", "OWNER", width = max_owner_colsize),
+            Column::Modified => date_header("MODIFIED"),
+            Column::Created => date_header("CREATED"),
+            Column::Accessed => date_header("ACCESSED"),
+            Column::Expires => format!("{:>7}", "EXPIRES"),
+            Column::Cert => format!("{:>7}", "CERT"),
+            Column::Layer => format!("{:>5}", "LAYER"),
+            Column::Xattrs => format!("{:>5}", "XATTR"),
+            Column::Name => "NAME".to_string(),
+        }
+    }
+}
+
+/// Type bits of st_mode and the letter ls -l shows for them.
+const FILE_TYPES: [(u32, char); 7] = [
+    (0o100000, '-'),
+    (0o040000, 'd'),
+    (0o120000, 'l'),
+    (0o010000, 'p'),
+    (0o140000, 's'),
+    (0o020000, 'c'),
+    (0o060000, 'b'),
+];
+
+// Mode as ls -l shows it, e.g. "drwxr-xr-x" or "-rwsr-xr-x" for a setuid executable.
+fn fmt_symbolic(mode: u32) -> String {
+    let file_type = FILE_TYPES
+        .iter()
+        .find(|&&(bits, _)| mode & 0o170000 == bits)
+        .map_or('?', |&(_, letter)| letter);
+    let mut symbolic = String::with_capacity(10);
+    symbolic.push(file_type);
+    // Each class with its execute letter when a special bit is set (lowercase if executable)
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift & 0o7;
+        symbolic.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        symbolic.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        symbolic.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => letter,
+            (true, false) => letter.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    symbolic
+}
+
+fn parse_file_colors(s: &str) -> Result<HashMap<String, String>, String> {
+    let mut map = HashMap::new();
+    for kv in s.split(',') {
+        let parts: Vec<&str> = kv.split('=').collect();
+        if parts.len() != 2 {
+            return Err(format!("Invalid format: {}", kv));
+        }
+        map.insert(parts[0].to_string(), parts[1].to_string());
+    }
+    Ok(map)
+}
+
+// Time elapsed in its largest unit, e.g. "3h ago", or "in 2d" for dates in the future.
+fn fmt_elapsed(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().abs();
+    let (value, unit) = match seconds {
+        0..60 => (seconds, "s"),
+        60..3600 => (seconds / 60, "m"),
+        3600..86400 => (seconds / 3600, "h"),
+        _ => match seconds / 86400 {
+            days @ 0..30 => (days, "d"),
+            days @ 30..365 => (days / 30, "mo"),
+            days => (days / 365, "y"),
+        },
+    };
+    if elapsed < chrono::Duration::zero() {
+        format!("in {}{}", value, unit)
+    } else {
+        format!("{}{} ago", value, unit)
+    }
+}
+
+// A local time, for --deterministic.
+fn parse_now(s: &str) -> Result<DateTime<Local>, String> {
+    if let Some(seconds) = s.strip_prefix('@') {
+        let seconds = seconds.parse().map_err(|_| format!("invalid timestamp {}", s))?;
+        return Local
+            .timestamp_opt(seconds, 0)
+            .single()
+            .ok_or_else(|| format!("timestamp out of range: {}", s));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(s, format) {
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .ok_or_else(|| format!("{} doesn't exist in the local time zone", s));
+        }
+    }
+    Err(format!("expected YYYY-MM-DD HH:MM[:SS] or @SECONDS, got {}", s))
+}
+
+// Rejects unknown specifiers upfront, as chrono only fails when a date gets formatted.
+fn parse_date_format(s: &str) -> Result<String, String> {
+    use chrono::format::{Item, StrftimeItems};
+    if StrftimeItems::new(s).any(|item| item == Item::Error) {
+        return Err(format!("invalid strftime format {}", s));
+    }
+    Ok(s.to_string())
+}
+
+fn parse_separator_width(s: &str) -> Result<SeparatorWidth, String> {
+    if s == "auto" {
+        return Ok(SeparatorWidth::Auto);
+    }
+    s.parse()
+        .map(SeparatorWidth::Fixed)
+        .map_err(|_| format!("expected a number or \"auto\", got {}", s))
+}
+
+fn main() {
+    // Rust ignores SIGPIPE, turning a closed pipe (e.g. `myls | head`) into write errors and
+    // println! panics. Like other command line tools, just stop when the reader goes away.
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let exit_code = run();
+    process::exit(exit_code);
+}
+
+fn run() -> i32 {
+    let args = Args::parse();
+
+    if args.version {
+        println!("myls {}", env!("CARGO_PKG_VERSION"));
+        return 0;
+    }
+
+    logger::init(args.verbose);
+    diagnostics::set_quiet(args.quiet);
+
+    let config = Config::load();
+    let args = match view::expand(env::args_os().collect(), &config.view) {
+        Ok(Some(argv)) => Args::parse_from(argv),
+        Ok(None) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+
+    let display_args = match &args.command {
+        None => &args.list.display,
+        Some(Command::List(list_args)) => &list_args.display,
+        Some(Command::Tree(tree_args)) => &tree_args.display,
+        Some(Command::Du(du_args)) => &du_args.display,
+        Some(Command::Diff(diff_args)) => &diff_args.display,
+        Some(Command::Find(find_args)) => &find_args.display,
+        Some(Command::Recent(recent_args)) => &recent_args.display,
+        Some(Command::RenamePreview(rename_args)) => &rename_args.display,
+        Some(Command::Bench(bench_args)) => &bench_args.display,
+        Some(Command::Config { action }) => return run_config(action),
+        Some(Command::Cache { action }) => return run_cache(action),
+        Some(Command::Doctor) => return doctor::run(),
+    };
+    let settings = Settings::new(display_args, config);
+    logger::set_color(settings.color);
+
+    // Per-entry errors are held back so they don't interleave with the listing: machine readable
+    // outputs carry them themselves, the table gets them on stderr once it's written
+    diagnostics::collect_errors(true);
+
+    let exit_code = match &args.command {
+        None => run_list(&args.list, &settings),
+        Some(Command::List(list_args)) => run_list(list_args, &settings),
+        Some(Command::Tree(tree_args)) => tree::run(tree_args, &settings),
+        Some(Command::Du(du_args)) => du::run(du_args, &settings),
+        Some(Command::Diff(diff_args)) => diff::run(diff_args, &settings),
+        Some(Command::Find(find_args)) => find::run(find_args, &settings),
+        Some(Command::Recent(recent_args)) => recent::run(recent_args, &settings),
+        Some(Command::RenamePreview(rename_args)) => rename::run(rename_args, &settings),
+        Some(Command::Bench(bench_args)) => bench::run(bench_args, &settings),
+        Some(Command::Config { .. }) | Some(Command::Cache { .. }) | Some(Command::Doctor) => {
+            unreachable!()
+        }
+    };
+
+    diagnostics::print_collected();
+    exit_code
+}
+
+/// Display settings resolved from the command line and the config file.
+struct Settings {
+    icons: Icons,
+    truncation: Truncation,
+    owner_names: OwnerNames,
+    /// Shorter names shown for owners and groups in tables
+    owner_aliases: HashMap<String, String>,
+    elide_owner: bool,
+    link_size: bool,
+    size_style: SizeStyle,
+    perm_style: PermStyle,
+    sort_order: SortOrder,
+    sort_case: SortCase,
+    sort_dotfiles: SortDotfiles,
+    time_field: TimeField,
+    file_colors: HashMap<String, String>,
+    column_priority: Vec<Column>,
+    output: OutputFormat,
+    fail_if_empty: bool,
+    /// Entries a table is cut to, with --max-entries
+    max_entries: Option<usize>,
+    print0: bool,
+    filters: Filters,
+    show_counts: bool,
+    show_target_counts: bool,
+    dir_size: DirSize,
+    /// Time limit of deep directory sizes in listings
+    dir_size_timeout: Option<Duration>,
+    show_self: bool,
+    separator: Separator,
+    scan: ScanOptions,
+    color: bool,
+    palette: Palette,
+    row_color: RowColor,
+    theme: Theme,
+    date_style: DateStyle,
+    expires: Option<i64>,
+    cert_expiry: Option<i64>,
+    show_layer: bool,
+    show_xattrs: bool,
+    show_links: bool,
+    show_created: bool,
+    show_accessed: bool,
+    /// Columns picked with --columns, all of them if None
+    columns: Option<Vec<Column>>,
+    /// Layout of the rows given with --format, instead of the table
+    template: Option<Template>,
+    /// Time dates are compared to, frozen with --deterministic
+    now: DateTime<Local>,
+    /// Output independent of the terminal, with --deterministic
+    deterministic: bool,
+    highlight_patterns: Vec<Pattern>,
+    /// Mounted filesystems, to tell those without permissions
+    mounts: Mounts,
+}
+
+/// Line printed between the listed directory and its contents.
+struct Separator {
+    character: char,
+    width: SeparatorWidth,
+    color: Option<String>,
+    palette: Palette,
+}
+
+impl Separator {
+    fn render(&self) -> String {
+        let width = match self.width {
+            SeparatorWidth::Fixed(width) => width,
+            SeparatorWidth::Auto => terminal_width().unwrap_or(60),
+        };
+        let line = self.character.to_string().repeat(width);
+        match &self.color {
+            Some(color) => {
+                let line = format!("\x1b[{}{}{}", color, line, COLOR_RESET);
+                self.palette.apply(&line)
+            }
+            None => line,
+        }
+    }
+}
+
+/// Options deciding which directory entries are read at all.
+#[derive(Clone, Default)]
+struct ScanOptions {
+    show_hidden: bool,
+    /// Name patterns entries must match one of, when not empty
+    match_patterns: Vec<Pattern>,
+    /// Name patterns hiding the entries they match
+    exclude_patterns: Vec<Pattern>,
+    /// Keep entries deleted while being listed, as placeholders
+    show_vanished: bool,
+    /// Name patterns from the global ignore file
+    ignore_patterns: Vec<Pattern>,
+}
+
+impl ScanOptions {
+    // Whether the name passes the ignore file and the --exclude and --match patterns
+    fn accepts(&self, path: &Path, file_name: &str) -> bool {
+        if let Some(pattern) = self.ignore_patterns.iter().find(|p| p.matches(file_name)) {
+            debug!("Skipped {}: ignored by pattern {}", path.display(), pattern);
+            return false;
+        }
+        if let Some(pattern) = self.exclude_patterns.iter().find(|p| p.matches(file_name)) {
+            trace!("Skipped {}: excluded by pattern {}", path.display(), pattern);
+            return false;
+        }
+        if !self.matches(file_name) {
+            trace!("Skipped {}: no --match pattern matches", path.display());
+            return false;
+        }
+        true
+    }
+
+    // Whether the name matches one of the --match patterns, if any
+    fn matches(&self, file_name: &str) -> bool {
+        self.match_patterns.is_empty() || self.match_patterns.iter().any(|p| p.matches(file_name))
+    }
+}
+
+impl Settings {
+    // Command line options take precedence over the config file
+    fn new(args: &DisplayArgs, config: Config) -> Self {
+        if args.file_colors.is_some() {
+            debug!("File colors taken from the command line");
+        } else if config.file_colors.is_some() {
+            debug!("File colors taken from the config file");
+        }
+        if !args.icons && config.icons.is_some() {
+            debug!("Icons setting taken from the config file");
+        }
+        if let Some(view) = &args.view {
+            debug!("Options of view {} taken from the config file", view);
+        }
+
+        let deterministic = args.deterministic.is_some();
+        let color = if deterministic {
+            args.color == ColorWhen::Always
+        } else {
+            args.color.enabled()
+        };
+        if !color {
+            debug!("Colors disabled");
+        }
+        let palette = if color && !deterministic {
+            Palette::detect()
+        } else {
+            Palette::Extended
+        };
+        let now = args.deterministic.unwrap_or_else(Local::now);
+        let listed = |column| {
+            args.columns.as_ref().is_some_and(|c| c.contains(&column))
+                || args.format.as_ref().is_some_and(|t| t.columns().any(|c| c == column))
+        };
+
+        Settings {
+            icons: Icons {
+                enabled: args.icons || config.icons.unwrap_or(false),
+                hidden: args.no_icons_for.clone().or(config.no_icons_for).unwrap_or_default(),
+                placeholder: args
+                    .icon_placeholder
+                    .clone()
+                    .or(config.icon_placeholder)
+                    .unwrap_or_else(|| Icons::default().placeholder),
+            },
+            truncation: Truncation {
+                max_length: args.max_name_length,
+                mode: args.truncate,
+                ellipsis: args.ellipsis.clone(),
+            },
+            owner_names: OwnerNames::new(args),
+            owner_aliases: config.owner_aliases.unwrap_or_default(),
+            elide_owner: args.elide_owner,
+            link_size: args.link_size,
+            size_style: args.size_style,
+            perm_style: args.perm_style,
+            sort_order: SortOrder {
+                by: args.sort,
+                reverse: args.reverse,
+                group_dirs: args.group_dirs,
+            },
+            sort_case: if args.case_sensitive_sort {
+                SortCase::Sensitive
+            } else {
+                args.sort_case
+            },
+            sort_dotfiles: args.sort_dotfiles,
+            time_field: args.time,
+            file_colors: args.file_colors.clone().or(config.file_colors).unwrap_or_default(),
+            column_priority: args
+                .column_priority
+                .clone()
+                .or(config.column_priority)
+                .unwrap_or_else(|| Column::DEFAULT_PRIORITY.to_vec()),
+            output: if args.json {
+                OutputFormat::Json
+            } else if args.ndjson {
+                OutputFormat::Ndjson
+            } else {
+                args.output
+            },
+            fail_if_empty: args.fail_if_empty,
+            max_entries: args.max_entries.filter(|_| !args.all_entries),
+            print0: args.print0,
+            filters: Filters::new(&args.filter, now),
+            show_counts: args.counts,
+            show_target_counts: args.target_counts,
+            dir_size: if args.dir_sizes {
+                DirSize::Deep
+            } else {
+                args.dir_size
+            },
+            dir_size_timeout: args.dir_size_timeout.map(Duration::from_secs),
+            show_self: !args.no_self,
+            separator: Separator {
+                character: args.separator_char,
+                width: match args.separator_width {
+                    SeparatorWidth::Auto if deterministic => SeparatorWidth::Fixed(60),
+                    width => width,
+                },
+                color: args.separator_color.clone().filter(|_| color),
+                palette,
+            },
+            color,
+            palette,
+            row_color: args.row_color,
+            theme: args
+                .writable_warning
+                .or(config.writable_warning)
+                .unwrap_or_default()
+                .apply(Theme::named(args.theme.or(config.theme).unwrap_or_default())),
+            date_style: DateStyle::new(args),
+            expires: args.expires,
+            cert_expiry: args.cert_expiry.or(listed(Column::Cert).then_some(30)),
+            show_layer: args.layer || listed(Column::Layer),
+            show_xattrs: args.xattrs || listed(Column::Xattrs),
+            show_links: args.links || listed(Column::Links),
+            show_created: args.show_created || listed(Column::Created),
+            show_accessed: args.show_accessed || listed(Column::Accessed),
+            columns: args.columns.clone(),
+            template: args.format.clone(),
+            now,
+            deterministic,
+            highlight_patterns: args.highlight.clone(),
+            mounts: Mounts::load(),
+            scan: ScanOptions {
+                show_hidden: args.filter.all,
+                match_patterns: args.filter.matches.clone(),
+                exclude_patterns: args.filter.exclude.clone(),
+                show_vanished: args.show_vanished,
+                ignore_patterns: if args.filter.no_ignore {
+                    Vec::new()
+                } else {
+                    config::load_ignore_patterns()
+                },
+            },
+        }
+    }
+
+    // Parts of the name matched by the first matching --match pattern
+    fn match_ranges(&self, raw_info: &RawInfo) -> Vec<Range<usize>> {
+        let file_name = raw_info.path.file_name().unwrap_or_default().to_string_lossy();
+        self.scan
+            .match_patterns
+            .iter()
+            .map(|pattern| filters::match_ranges(pattern, &file_name))
+            .find(|ranges| !ranges.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Shortens the name of an owner or a group for tables, as aliased in the config file.
+    fn owner_alias(&self, name: &mut String) {
+        if let Some(alias) = self.owner_aliases.get(name.as_str()) {
+            name.clone_from(alias);
+        }
+    }
+
+    /// Header row of a table, in inverted colors for more contrast when colors are enabled.
+    fn header(&self, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", HEADER_BACKGROUND, text, COLOR_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Whether the table has the column, before dropping those that don't fit the terminal.
+    fn shows(&self, column: Column) -> bool {
+        let enabled = match column {
+            Column::Created => self.show_created,
+            Column::Accessed => self.show_accessed,
+            Column::Expires => self.expires.is_some(),
+            Column::Cert => self.cert_expiry.is_some(),
+            Column::Layer => self.show_layer,
+            Column::Xattrs => self.show_xattrs,
+            Column::Links => self.show_links,
+            _ => true,
+        };
+        enabled && self.columns.as_ref().is_none_or(|columns| columns.contains(&column))
+    }
+
+    fn is_highlighted(&self, pinfo: &ProcessedInfo) -> bool {
+        let file_name = pinfo.rinfo.path.file_name().unwrap_or_default().to_string_lossy();
+        self.highlight_patterns.iter().any(|p| p.matches(&file_name))
+    }
+
+    /// Cuts a table to --max-entries. Returns how many entries were dropped.
+    fn cut_listing(&self, processed_infos: &mut Vec<ProcessedInfo>) -> u64 {
+        if self.output == OutputFormat::Table && !self.print0 {
+            cut_entries(processed_infos, self.max_entries)
+        } else {
+            0
+        }
+    }
+
+    // The main dir is always shown, to give context to its filtered contents
+    fn keep(&self, raw_info: &RawInfo) -> bool {
+        raw_info.is_main_dir || self.filters.matches(raw_info)
+    }
+
+    fn process(&self, mut raw_info: RawInfo) -> ProcessedInfo {
+        if self.show_counts && raw_info.is_directory && !raw_info.is_remote() {
+            raw_info.entry_counts = count_entries(&raw_info.path);
+        }
+
+        // Sizes already computed (e.g. by du) are kept
+        let mut shallow = false;
+        if raw_info.is_directory && raw_info.dir_size.is_none() {
+            raw_info.dir_size = match self.dir_size {
+                DirSize::None => None,
+                DirSize::Shallow => {
+                    shallow = true;
+                    Some(du::shallow_dir_size(&raw_info.path))
+                }
+                DirSize::Deep => Some(du::dir_size(&raw_info.path)),
+            };
+        }
+
+        let mut processed_info = ProcessedInfo::new(
+            raw_info,
+            &self.icons,
+            &self.truncation,
+            self.owner_names,
+            self.link_size,
+            self.sort_case,
+            self.sort_dotfiles,
+        );
+        if shallow {
+            processed_info.size_marker = "≈";
+        }
+        processed_info.time = processed_info.rinfo.time(self.time_field);
+        // The target is only read when asked, as it may be slow (e.g. on a network mount).
+        // Remote targets can't be read, local paths of the same name being unrelated.
+        let rinfo = &processed_info.rinfo;
+        let local_link = rinfo.is_symlink && !rinfo.is_remote();
+        if self.show_target_counts && local_link && rinfo.path.is_dir() {
+            if let Some(counts) = count_entries(&rinfo.path) {
+                let total = counts.files + counts.dirs;
+                let noun = if total == 1 { "item" } else { "items" };
+                let suffix = format!(" ({} {})", fmt_thousands(total), noun);
+                processed_info.target_name.push_str(&suffix);
+            }
+        }
+
+        // Mode bits made up by the filesystem (e.g. FAT) would be misleading
+        let rinfo = &processed_info.rinfo;
+        if !rinfo.vanished {
+            if self.perm_style == PermStyle::Symbolic {
+                processed_info.permissions = fmt_symbolic(rinfo.mode);
+            }
+            if let Some(mount) = self.mounts.without_permissions(&rinfo.path) {
+                processed_info.permissions = mount.attributes(rinfo.permissions()).to_string();
+            }
+        }
+        if !processed_info.rinfo.is_main_dir {
+            processed_info.match_ranges = self.match_ranges(&processed_info.rinfo);
+        }
+        processed_info
+    }
+
+    // Process the raw data into information needed for printing, sorted.
+    // Deep sizes of the directories without one, walked in parallel up to the time limit.
+    // Tells for each entry whether its size is complete.
+    fn add_deep_sizes(&self, raw_infos: &mut [RawInfo]) -> Vec<bool> {
+        let mut complete = vec![true; raw_infos.len()];
+        if self.dir_size != DirSize::Deep {
+            return complete;
+        }
+        let mut indices: Vec<usize> = (0..raw_infos.len())
+            .filter(|&i| raw_infos[i].is_directory && raw_infos[i].dir_size.is_none())
+            .collect();
+        // The listed directory, holding all the others, would use up the time limit first
+        indices.sort_by_key(|&i| raw_infos[i].is_main_dir);
+        let directories: Vec<PathBuf> =
+            indices.iter().map(|&i| raw_infos[i].path.clone()).collect();
+        let sizes = du::dir_sizes(&directories, self.dir_size_timeout);
+        for (i, (size, done)) in indices.into_iter().zip(sizes) {
+            raw_infos[i].dir_size = Some(size);
+            complete[i] = done;
+        }
+        complete
+    }
+
+    fn process_sorted(&self, mut raw_infos: Vec<RawInfo>) -> Vec<ProcessedInfo> {
+        let complete = self.add_deep_sizes(&mut raw_infos);
+        let mut processed_infos: Vec<ProcessedInfo> =
+            raw_infos.into_iter().map(|raw_info| self.process(raw_info)).collect();
+        for (processed_info, complete) in processed_infos.iter_mut().zip(complete) {
+            if !complete {
+                processed_info.size_marker = ">";
+            }
+        }
+
+        // Sort: main dir first, then directories (and links to directories), then by the chosen key
+        processed_infos.sort_by(|a, b| a.compare(b, self.sort_order));
+
+        processed_infos
+    }
+}
+
+fn run_list(args: &ListArgs, settings: &Settings) -> i32 {
+    let exec = match (&args.exec, &args.exec_batch) {
+        (Some(command), _) => Some((command, exec::Exec::parse(command, false))),
+        (None, Some(command)) => Some((command, exec::Exec::parse(command, true))),
+        (None, None) => None,
+    };
+    let exec = match exec {
+        Some((command, Err(e))) => {
+            eprintln!("Error: invalid command {}: {}", command, e);
+            return 1;
+        }
+        Some((_, Ok(_))) if args.paths.iter().any(|path| sftp::is_url(path)) => {
+            eprintln!("Error: commands can only be run on local entries");
+            return 1;
+        }
+        Some((_, Ok(exec))) => Some(exec),
+        None => None,
+    };
+
+    let mut paths = resolve_paths(&args.paths);
+    if !args.keep_duplicates {
+        paths = dedup_paths(paths);
+    }
+    let source = match source::for_paths(&args.paths) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    let start = Instant::now();
+    let raw_infos = if args.recursive {
+        scan_recursive(&paths, &settings.scan, source.as_ref())
+    } else {
+        scan_paths(&paths, &settings.scan, source.as_ref())
+    };
+    let mut raw_infos = match raw_infos {
+        Some(raw_infos) => raw_infos,
+        None => return 1,
+    };
+    raw_infos.retain(|raw_info| settings.keep(raw_info));
+    info!("Scanned {} entries in {:?}", raw_infos.len(), start.elapsed());
+
+    if args.by_owner {
+        return owners::print(&raw_infos, settings);
+    }
+    if args.perm_summary {
+        return audit::print(&raw_infos, settings);
+    }
+    if args.stats {
+        return stats::print(&raw_infos, settings);
+    }
+
+    let start = Instant::now();
+    let mut processed_infos = settings.process_sorted(raw_infos);
+    if !args.expand.is_empty() {
+        processed_infos =
+            expand_directories(processed_infos, &args.expand, settings, source.as_ref());
+    }
+    info!("Processed and sorted entries in {:?}", start.elapsed());
+
+    // Commands only run on the entries shown, which the summary sums up
+    let left_out = settings.cut_listing(&mut processed_infos);
+    let summary = if args.no_summary
+        || settings.output != OutputFormat::Table
+        || settings.print0
+        || settings.template.is_some()
+    {
+        None
+    } else {
+        fmt_summary(&processed_infos)
+    };
+
+    // Entries that commands run on, in the listing's order
+    let exec_paths: Vec<PathBuf> = match exec {
+        Some(_) => processed_infos
+            .iter()
+            .filter(|pinfo| !pinfo.rinfo.is_main_dir && !pinfo.rinfo.vanished)
+            .map(|pinfo| pinfo.rinfo.path.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let start = Instant::now();
+    let exit_code = print_cut_listing(processed_infos, left_out, settings);
+    if let Some(summary) = summary.filter(|_| exit_code != 1) {
+        println!("{}", settings.separator.render());
+        println!("{}", summary);
+    }
+    info!("Printed listing in {:?}", start.elapsed());
+
+    match exec {
+        Some(exec) if exit_code != 1 => {
+            let _ = io::stdout().flush();
+            exec.run(&exec_paths, args.yes).max(exit_code)
+        }
+        _ => exit_code,
+    }
+}
+
+/// Footer with the number of listed files and directories and the combined size of the files,
+/// as with `du -c`, if anything was listed besides the listed directory. The size of
+/// directories is left out, since only their own entry was read.
+fn fmt_summary(processed_infos: &[ProcessedInfo]) -> Option<String> {
+    let entries: Vec<&RawInfo> = processed_infos
+        .iter()
+        .map(|pinfo| &pinfo.rinfo)
+        .filter(|rinfo| !rinfo.is_main_dir && !rinfo.vanished)
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+
+    let (dirs, files): (Vec<&RawInfo>, Vec<&RawInfo>) =
+        entries.into_iter().partition(|rinfo| rinfo.is_directory);
+    let count = |count: usize, noun: &str| {
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{} {}{}", fmt_thousands(count as u64), noun, plural)
+    };
+    let (size, unit) = ProcessedInfo::fmt_size_and_unit(files.iter().map(|r| r.size).sum());
+    Some(format!(
+        "{}, {}, {}{}",
+        count(files.len(), "file"),
+        count(dirs.len(), "dir"),
+        size,
+        unit
+    ))
+}
+
+/// Collects the entries to list for the given paths.
+/// A single directory has its contents listed, after the dir info itself.
+/// Returns None (after reporting it) if a path doesn't exist.
+fn scan_paths(
+    paths: &[PathBuf],
+    scan: &ScanOptions,
+    source: &dyn FileSource,
+) -> Option<Vec<RawInfo>> {
+    let mut raw_infos: Vec<RawInfo> = Vec::new();
+
+    for path in paths {
+        match source.exists(path) {
+            Ok(true) => (),
+            Ok(false) => {
+                eprintln!("Error: {} does not exist", path.display());
+                return None;
+            }
+            Err(e) => {
+                eprintln!("Error: could not read {}: {}", path.display(), e);
+                return None;
+            }
+        }
+
+        // Single dir mode: list dir contents, after dir info itself.
+        // A link to a directory is followed, like ls does, while its row still shows the link.
+        if source.is_dir(path) && paths.len() == 1 {
+            if let Some(mut main_dir_info) = source.file_info(path) {
+                if main_dir_info.is_symlink {
+                    debug!("Following link {} to list its target", path.display());
+                }
+                main_dir_info.is_main_dir = true;
+                raw_infos.push(main_dir_info);
+            }
+            raw_infos.extend(source.list_directory(path, scan));
+        }
+        // Normal mode: list details of given files and dirs
+        else {
+            if let Some(file_info) = source.file_info(path) {
+                raw_infos.push(file_info);
+            }
+        }
+    }
+
+    Some(raw_infos)
+}
+
+/// Scans the paths as `scan_paths` does, adding the contents of the listed directories'
+/// subdirectories, recursively. Entries found along the way are labeled with their path from
+/// the main dir. Directories not matching --match are still walked through, only left out of
+/// the listing.
+fn scan_recursive(
+    paths: &[PathBuf],
+    scan: &ScanOptions,
+    source: &dyn FileSource,
+) -> Option<Vec<RawInfo>> {
+    let walk = ScanOptions {
+        match_patterns: Vec::new(),
+        ..scan.clone()
+    };
+    let raw_infos = scan_paths(paths, &walk, source)?;
+    let root = match raw_infos.iter().find(|raw_info| raw_info.is_main_dir) {
+        Some(main_dir) => main_dir.path.clone(),
+        None => PathBuf::new(),
+    };
+
+    let mut all_infos = Vec::new();
+    for raw_info in raw_infos {
+        let subdir = raw_info.is_directory && !raw_info.is_symlink && !raw_info.is_main_dir;
+        let path = raw_info.path.clone();
+        // Paths given on the command line are listed whatever their name
+        let given = raw_info.is_main_dir || root.as_os_str().is_empty();
+        if given || scan.matches(&file_name(&raw_info)) {
+            all_infos.push(raw_info);
+        }
+        if subdir {
+            add_subdirectory(&root, &path, scan, &walk, source, &mut all_infos);
+        }
+    }
+    Some(all_infos)
+}
+
+fn add_subdirectory(
+    root: &Path,
+    directory: &Path,
+    scan: &ScanOptions,
+    walk: &ScanOptions,
+    source: &dyn FileSource,
+    raw_infos: &mut Vec<RawInfo>,
+) {
+    for mut raw_info in source.list_directory(directory, walk) {
+        let relative_path = raw_info.path.strip_prefix(root).unwrap_or(&raw_info.path);
+        raw_info.label = Some(relative_path.display().to_string());
+        raw_info.depth = relative_path.components().count().saturating_sub(1);
+
+        let subdir = raw_info.is_directory && !raw_info.is_symlink;
+        let path = raw_info.path.clone();
+        if scan.matches(&file_name(&raw_info)) {
+            raw_infos.push(raw_info);
+        }
+        if subdir {
+            add_subdirectory(root, &path, scan, walk, source, raw_infos);
+        }
+    }
+}
+
+fn file_name(raw_info: &RawInfo) -> String {
+    raw_info.path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Puts the sorted contents of the directories named with --expand right after their row,
+/// drawn as in the tree subcommand.
+fn expand_directories(
+    processed_infos: Vec<ProcessedInfo>,
+    names: &[String],
+    settings: &Settings,
+    source: &dyn FileSource,
+) -> Vec<ProcessedInfo> {
+    let mut expanded = Vec::with_capacity(processed_infos.len());
+    let mut found = HashSet::new();
+    for pinfo in processed_infos {
+        let name = pinfo.rinfo.path.file_name().map(|name| name.to_string_lossy().to_string());
+        let name = name.filter(|name| {
+            pinfo.rinfo.is_directory && !pinfo.rinfo.is_main_dir && names.contains(name)
+        });
+        let path = pinfo.rinfo.path.clone();
+        expanded.push(pinfo);
+        let Some(name) = name else { continue };
+
+        let mut children = source.list_directory(&path, &settings.scan);
+        children.retain(|child| settings.keep(child));
+        for child in &mut children {
+            child.depth = 1;
+        }
+        let children = settings.process_sorted(children);
+        let count = children.len();
+        for (i, mut child) in children.into_iter().enumerate() {
+            let connector = if i + 1 == count { "└── " } else { "├── " };
+            child.name = format!("{}{}", connector, child.name);
+            expanded.push(child);
+        }
+        found.insert(name);
+    }
+
+    for name in names.iter().filter(|name| !found.contains(*name)) {
+        diagnostics::warn(&format!("cannot expand {}: no such directory in the listing", name));
+    }
+    expanded
+}
+
+// The default "." is expanded so the main dir row shows the actual directory name.
+fn resolve_paths(paths: &[String]) -> Vec<PathBuf> {
+    if paths.len() == 1 && paths[0] == "." {
+        vec![env::current_dir().unwrap_or_else(|_| PathBuf::from("."))]
+    } else if cfg!(windows) {
+        // Windows shells pass wildcards along instead of expanding them
+        paths.iter().flat_map(|path| expand_glob(path)).collect()
+    } else {
+        paths.iter().map(PathBuf::from).collect()
+    }
+}
+
+// Paths without those naming the same entry as an earlier one (e.g. "a", "./a" and "/dir/a").
+// Links are kept apart from their targets, as they are entries of their own.
+fn dedup_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut unique = Vec::with_capacity(paths.len());
+    for path in paths {
+        let key = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                fs::canonicalize(parent).map(|parent| parent.join(name))
+            }
+            _ => fs::canonicalize(&path),
+        }
+        .unwrap_or_else(|_| path.clone());
+        match seen.get(&key) {
+            Some(first) => info!("Skipped {}: same as {}", path.display(), first.display()),
+            None => {
+                seen.insert(key, path.clone());
+                unique.push(path);
+            }
+        }
+    }
+    unique
+}
+
+// Paths matching a wildcard pattern, in alphabetical order. Paths without wildcards, invalid
+// patterns and patterns matching nothing are kept as they are, to be reported if missing.
+fn expand_glob(path: &str) -> Vec<PathBuf> {
+    if !path.contains(['*', '?', '[']) {
+        return vec![PathBuf::from(path)];
+    }
+
+    let matches: Vec<PathBuf> = match glob::glob(path) {
+        Ok(paths) => paths.flatten().collect(),
+        Err(_) => Vec::new(),
+    };
+    if matches.is_empty() {
+        vec![PathBuf::from(path)]
+    } else {
+        matches
+    }
+}
+
+/// Prints the given entries in order, in the output format chosen by the user.
+/// Returns the exit code for the listing.
+fn print_listing(mut processed_infos: Vec<ProcessedInfo>, settings: &Settings) -> i32 {
+    let left_out = settings.cut_listing(&mut processed_infos);
+    print_cut_listing(processed_infos, left_out, settings)
+}
+
+/// Prints entries already cut to --max-entries, followed by a notice of those left out.
+fn print_cut_listing(
+    mut processed_infos: Vec<ProcessedInfo>,
+    left_out: u64,
+    settings: &Settings,
+) -> i32 {
+    let is_empty = processed_infos.iter().all(|pinfo| pinfo.rinfo.is_main_dir);
+    if !settings.show_self {
+        processed_infos.retain(|pinfo| !pinfo.rinfo.is_main_dir);
+    }
+
+    let written = match settings.output {
+        _ if settings.print0 => print_paths(&processed_infos),
+        OutputFormat::Table => {
+            let out = BufWriter::new(io::stdout().lock());
+            write_table(out, processed_infos, settings).and_then(|()| match left_out {
+                0 => Ok(()),
+                n => {
+                    let notice = format!("… and {} more (use --all-entries)", fmt_thousands(n));
+                    writeln!(io::stdout(), "{}", notice)
+                }
+            })
+        }
+        OutputFormat::Json => json::print(&processed_infos, false),
+        OutputFormat::Ndjson => json::print(&processed_infos, true),
+        OutputFormat::Csv => csv::print(&processed_infos, ','),
+        OutputFormat::Tsv => csv::print(&processed_infos, '\t'),
+    };
+    match written {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return 0,
+        Err(e) => {
+            eprintln!("Error: could not write output: {}", e);
+            return 1;
+        }
+        Ok(()) => (),
+    }
+
+    if settings.fail_if_empty && is_empty {
+        EXIT_EMPTY
+    } else {
+        0
+    }
+}
+
+/// Keeps the main dir and the first entries after it, up to the maximum.
+/// Returns how many entries were dropped.
+fn cut_entries(processed_infos: &mut Vec<ProcessedInfo>, max_entries: Option<usize>) -> u64 {
+    let main_dirs = processed_infos.iter().take_while(|pinfo| pinfo.rinfo.is_main_dir).count();
+    let len = match max_entries {
+        Some(max_entries) => processed_infos.len().min(main_dirs + max_entries),
+        None => return 0,
+    };
+    let left_out = processed_infos.len() - len;
+    processed_infos.truncate(len);
+    left_out as u64
+}
+
+/// Prints the raw bytes of each path followed by a NUL, so any name survives the trip to `xargs -0`.
+fn print_paths(processed_infos: &[ProcessedInfo]) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    for pinfo in processed_infos.iter().filter(|pinfo| !pinfo.rinfo.is_main_dir) {
+        out.write_all(pinfo.rinfo.path.as_os_str().as_bytes())?;
+        out.write_all(b"\0")?;
+    }
+    out.flush()
+}
+
+/// Writes the header and the given entries in order, the main dir (if any) being the first one.
+/// The output is flushed once at the end, so it should be buffered.
+fn write_table(
+    mut out: impl Write,
+    mut processed_infos: Vec<ProcessedInfo>,
+    settings: &Settings,
+) -> io::Result<()> {
+    for pinfo in &mut processed_infos {
+        settings.owner_alias(&mut pinfo.username);
+        settings.owner_alias(&mut pinfo.groupname);
+    }
+    let max_owner_colsize = processed_infos
+        .iter()
+        .map(|pi| pi.username.len() + pi.groupname.len())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let common_owner = if settings.elide_owner && settings.template.is_none() {
+        common_owner(&processed_infos)
+    } else {
+        None
+    };
+
+    let style = TableStyle {
+        max_owner_colsize,
+        file_colors: settings.file_colors.clone(),
+        // Groups of the current process, to hint which group permissions apply to the user.
+        user_groups: get_process_groups(),
+        color: settings.color,
+        palette: settings.palette,
+        row_color: settings.row_color,
+        size_style: settings.size_style,
+        theme: settings.theme,
+        date_style: settings.date_style.clone(),
+        now: settings.now,
+        expires: settings.expires,
+        cert_expiry: settings.cert_expiry,
+        overlays: if settings.show_layer {
+            OverlayMounts::load()
+        } else {
+            OverlayMounts::default()
+        },
+        count_xattrs: settings.show_xattrs,
+    };
+
+    // Adds padding and colors to the output.
+    let mut displayable_infos: Vec<DisplayableInfo> = processed_infos
+        .into_iter()
+        .enumerate()
+        .map(|(i, pinfo)| {
+            let highlighted = settings.is_highlighted(&pinfo);
+            DisplayableInfo::new(i, pinfo, &style, highlighted)
+        })
+        .collect();
+
+    // Dates and permissions are right-aligned, their width depending on the style (and on how old
+    // dates are)
+    let mut widths = HashMap::new();
+    for column in Column::ALIGNED {
+        let width = displayable_infos
+            .iter()
+            .map(|dinfo| visible_width(dinfo.column(column)))
+            .chain(std::iter::once(column.header(0, &HashMap::new()).len()))
+            .max()
+            .unwrap_or(0);
+        for dinfo in &mut displayable_infos {
+            let cell = dinfo.column_mut(column);
+            let padding = width - visible_width(cell);
+            cell.insert_str(0, &" ".repeat(padding));
+        }
+        widths.insert(column, width);
+    }
+
+    // Drop lower priority columns that would make rows wrap on narrow terminals
+    let available = Column::ALL
+        .into_iter()
+        .filter(|&column| settings.shows(column))
+        .filter(|&column| column != Column::Owner || common_owner.is_none())
+        .collect();
+    let columns = fit_columns(
+        &displayable_infos,
+        max_owner_colsize,
+        &widths,
+        available,
+        &settings.column_priority,
+        if settings.deterministic { None } else { terminal_width() },
+    );
+
+    // Rows laid out by a template have no header, their fields being the user's choice
+    let render = |dinfo: &DisplayableInfo| match &settings.template {
+        Some(template) => dinfo.render_template(template),
+        None => dinfo.render(&columns),
+    };
+    if settings.template.is_none() {
+        // Print header with inverted colors for more contrast
+        let header = columns
+            .iter()
+            .map(|column| column.header(max_owner_colsize, &widths))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match &common_owner {
+            Some(owner) => writeln!(out, "{} (all owned by {})", settings.header(&header), owner)?,
+            None => writeln!(out, "{}", settings.header(&header))?,
+        }
+    }
+
+    // If the input is a single directory, print its own info before the content list
+    if !displayable_infos.is_empty() && displayable_infos[0].is_main_dir {
+        let main_dir_info = displayable_infos.remove(0);
+        writeln!(out, "{}", render(&main_dir_info))?;
+        if !displayable_infos.is_empty() {
+            writeln!(out, "{}", settings.separator.render())?;
+        }
+    }
+
+    // Print each file with formatted output
+    for dinfo in &displayable_infos {
+        writeln!(out, "{}", render(dinfo))?;
+    }
+    out.flush()
+}
+
+/// Owner and group shared by every entry, as the owner column shows them, if they all have the
+/// same. Vanished entries, whose owner is unknown, don't count.
+fn common_owner(processed_infos: &[ProcessedInfo]) -> Option<String> {
+    let mut owners = processed_infos
+        .iter()
+        .filter(|pinfo| !pinfo.rinfo.vanished)
+        .map(|pinfo| (&pinfo.username, &pinfo.groupname));
+    let first = owners.next()?;
+    if owners.all(|owner| owner == first) {
+        Some(format!("{}:{}", first.0, first.1))
+    } else {
+        None
+    }
+}
+
+fn run_config(action: &ConfigAction) -> i32 {
+    let result = match action {
+        ConfigAction::Export => config::export().map(|exported| println!("{}", exported)),
+        ConfigAction::Import { source } => config::import(source)
+            .map(|path| println!("Config imported to {}", path.display())),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+fn run_cache(action: &CacheAction) -> i32 {
+    let result = match action {
+        CacheAction::Status => cache::status().map(|status| println!("{}", status)),
+        CacheAction::Clear => {
+            cache::clear().map(|path| println!("Size cache cleared ({})", path.display()))
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+// #[derive(Debug)]
+#[derive(Clone)]
+struct RawInfo {
+    path: PathBuf,
+    /// Type, setuid/setgid/sticky bits and permissions, as in st_mode
+    mode: u32,
+    /// Number of hard links
+    links: u64,
+    /// Inode number, 0 where unknown (e.g. remote entries)
+    inode: u64,
+    /// Device holding the entry, 0 where unknown
+    dev: u64,
+    /// Device a character or block special file stands for, 0 for other entries
+    rdev: u64,
+    size: u64,
+    owner_uid: u32,
+    group_gid: u32,
+    modified_time: DateTime<Local>,
+    /// Birth time, where the platform and filesystem record it
+    created_time: Option<DateTime<Local>>,
+    /// Last access time
+    accessed_time: Option<DateTime<Local>>,
+    /// Last status change time
+    changed_time: Option<DateTime<Local>>,
+    is_directory: bool,
+    is_executable: bool,
+    is_symlink: bool,
+    is_main_dir: bool,
+    /// Total size of a directory's contents, when it was computed
+    dir_size: Option<u64>,
+    /// Name to display instead of the file name (e.g. a path relative to a search root)
+    label: Option<String>,
+    /// Directories between the listed one and the entry, found by recursive listings
+    depth: usize,
+    /// Number of files and subdirectories directly inside a directory, when counted
+    entry_counts: Option<EntryCounts>,
+    /// Listed by its directory, but deleted before its metadata could be read
+    vanished: bool,
+}
+
+impl RawInfo {
+    // Entry with only its path known, every other field being zero or empty, for sources to
+    // fill in.
+    fn empty(path: &Path) -> Self {
+        RawInfo {
+            path: path.to_path_buf(),
+            mode: 0,
+            links: 0,
+            inode: 0,
+            dev: 0,
+            rdev: 0,
+            size: 0,
+            owner_uid: 0,
+            group_gid: 0,
+            modified_time: Local::now(),
+            created_time: None,
+            accessed_time: None,
+            changed_time: None,
+            is_directory: false,
+            is_executable: false,
+            is_symlink: false,
+            is_main_dir: false,
+            dir_size: None,
+            label: None,
+            depth: 0,
+            entry_counts: None,
+            vanished: false,
+        }
+    }
+
+    // Placeholder for an entry whose metadata is gone, only its path is known.
+    fn vanished(path: &Path) -> Self {
+        RawInfo {
+            vanished: true,
+            ..Self::empty(path)
+        }
+    }
+
+    /// Whether the entry was read from another host (e.g. an sftp:// URL), its path not being
+    /// a local one.
+    fn is_remote(&self) -> bool {
+        sftp::is_url(&self.path.to_string_lossy())
+    }
+
+    /// Permissions of the user, group and others, without the type and special bits.
+    fn permissions(&self) -> u32 {
+        self.mode & 0o777
+    }
+
+    /// The chosen timestamp, if known.
+    fn time(&self, field: TimeField) -> Option<DateTime<Local>> {
+        if self.vanished {
+            return None;
+        }
+        match field {
+            TimeField::Mtime => Some(self.modified_time),
+            TimeField::Atime => self.accessed_time,
+            TimeField::Ctime => self.changed_time,
+            TimeField::Birth => self.created_time,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct EntryCounts {
+    files: u64,
+    dirs: u64,
+}
+
+struct ProcessedInfo {
+    rinfo: RawInfo,
+    permissions: String,
+    size: String,
+    size_unit: String,
+    username: String,
+    groupname: String,
+    name: String,
+    target_name: String,
+    is_executable: bool,
+    // Shown before the size when it's not the entry's own: "→" for a link's target,
+    // "≈" for a directory's immediate contents
+    size_marker: &'static str,
+    /// Timestamp shown as modification date and sorted by, as chosen with --time
+    time: Option<DateTime<Local>>,
+    sort_keys: (u8, u8, String),
+    // Byte ranges of the file name matched by the active name filter, if any.
+    match_ranges: Vec<Range<usize>>,
+}
+
+impl ProcessedInfo {
+    const KB: u64 = 1024;
+    const MB: u64 = Self::KB * 1024;
+    const GB: u64 = Self::MB * 1024;
+
+    fn new(
+        raw_info: RawInfo,
+        icons: &Icons,
+        truncation: &Truncation,
+        owner_names: OwnerNames,
+        link_size: bool,
+        sort_case: SortCase,
+        sort_dotfiles: SortDotfiles,
+    ) -> Self {
+        if raw_info.vanished {
+            return Self::vanished(raw_info, truncation);
+        }
+
+        // Format permissions as octal string, with a leading digit for setuid, setgid and sticky.
+        let permissions = format!("{:03o}", raw_info.mode & 0o7777);
+
+        // Links to regular files may borrow the size of their target
+        let target_size = if link_size && raw_info.is_symlink {
+            fs::metadata(&raw_info.path)
+                .ok()
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+        } else {
+            None
+        };
+        let (size, size_unit) = match target_size {
+            Some(target_size) => Self::fmt_size_and_unit(target_size),
+            None => Self::get_size_and_unit(&raw_info),
+        };
+
+        let username = owner_names.owner(raw_info.owner_uid);
+        let groupname = owner_names.group(raw_info.group_gid);
+
+        let target = if raw_info.is_symlink {
+            raw_info.path.read_link().ok()
+        } else {
+            None
+        };
+
+        // Resolved from the link itself, as relative targets are relative to the link's directory
+        let targets_folder = target.is_some() && raw_info.path.is_dir();
+
+        // Enshorten names if needed.
+        let base_name = raw_info.label.clone().unwrap_or_else(|| {
+            raw_info
+                .path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "/".to_string())
+        });
+
+        let name = truncation.apply(&base_name);
+
+        let target_name = match target {
+            Some(ref target) => truncation.apply(&target.display().to_string()),
+            None => String::new(),
+        };
+
+        // Format names with folder emoji if directory.
+        let name = if raw_info.is_directory {
+            icons.prefix(IconKind::Dir, raw_info.is_main_dir, name)
+        } else {
+            name
+        };
+
+        let target_name = if !target_name.is_empty() && targets_folder {
+            icons.prefix(IconKind::Target, raw_info.is_main_dir, target_name)
+        } else {
+            target_name
+        };
+
+        // Disconsider directories and folder links as executables.
+        let is_executable = raw_info.is_executable
+            && !raw_info.is_directory
+            && (target.is_none() || !targets_folder);
+
+        let (dot_rank, sort_name) = Self::get_name_sort_keys(&base_name, sort_case, sort_dotfiles);
+        let sort_keys = if raw_info.is_main_dir {
+            (0, dot_rank, sort_name)
+        } else if raw_info.is_directory || targets_folder {
+            (1, dot_rank, sort_name)
+        } else {
+            (2, dot_rank, sort_name)
+        };
+
+        ProcessedInfo {
+            time: Some(raw_info.modified_time),
+            rinfo: raw_info,
+            permissions,
+            size,
+            size_unit,
+            username,
+            groupname,
+            name,
+            target_name,
+            is_executable,
+            size_marker: if target_size.is_some() { "→" } else { "" },
+            sort_keys,
+            match_ranges: Vec::new(),
+        }
+    }
+
+    // Nothing but the name is known about entries that vanished while being listed.
+    fn vanished(raw_info: RawInfo, truncation: &Truncation) -> Self {
+        let base_name = raw_info
+            .path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        ProcessedInfo {
+            permissions: "---".to_string(),
+            size: String::new(),
+            size_unit: String::new(),
+            username: "?".to_string(),
+            groupname: "?".to_string(),
+            name: format!("{} (vanished)", truncation.apply(&base_name)),
+            target_name: String::new(),
+            is_executable: false,
+            size_marker: "",
+            time: None,
+            sort_keys: (2, 0, base_name.to_lowercase()),
+            match_ranges: Vec::new(),
+            rinfo: raw_info,
+        }
+    }
+
+    /// Listing order of two entries: main dir first, then directories and files as grouped
+    /// by the sort order, each group sorted by the given key and then by name.
+    fn compare(&self, other: &Self, order: SortOrder) -> Ordering {
+        let by_key = match order.by {
+            SortBy::Name => Ordering::Equal,
+            SortBy::Size => other.byte_size().cmp(&self.byte_size()),
+            SortBy::Time => other.time.cmp(&self.time),
+            SortBy::Ext => self.sort_extension().cmp(&other.sort_extension()),
+            SortBy::Owner => self.username.cmp(&other.username),
+            SortBy::Group => self.groupname.cmp(&other.groupname),
+            SortBy::Depth => self.rinfo.depth.cmp(&other.rinfo.depth),
+        };
+
+        let (_, dot_rank, name) = &self.sort_keys;
+        let (_, other_dot_rank, other_name) = &other.sort_keys;
+        // Equal names (e.g. differing in case, or in different directories) are told apart by
+        // their paths, and the same path given twice by what it pointed to at the time
+        let canonical = || {
+            self.rinfo
+                .path
+                .cmp(&other.rinfo.path)
+                .then(self.rinfo.inode.cmp(&other.rinfo.inode))
+        };
+        if self.rinfo.is_main_dir && other.rinfo.is_main_dir {
+            return canonical();
+        }
+        let within_group = by_key
+            .then_with(|| (dot_rank, name).cmp(&(other_dot_rank, other_name)))
+            .then_with(canonical);
+
+        let within_group = if order.reverse {
+            within_group.reverse()
+        } else {
+            within_group
+        };
+        self.group_rank(order.group_dirs)
+            .cmp(&other.group_rank(order.group_dirs))
+            .then(within_group)
+    }
+
+    fn group_rank(&self, group_dirs: GroupDirs) -> u8 {
+        match (self.sort_keys.0, group_dirs) {
+            (0, _) => 0,
+            (_, GroupDirs::None) => 1,
+            (group, GroupDirs::First) => group,
+            (group, GroupDirs::Last) => 3 - group,
+        }
+    }
+
+    fn byte_size(&self) -> u64 {
+        if self.rinfo.is_directory {
+            self.rinfo.dir_size.unwrap_or(0)
+        } else {
+            self.rinfo.size
+        }
+    }
+
+    // Taken from the name normalized for sorting, to follow --sort-case
+    fn sort_extension(&self) -> Option<&OsStr> {
+        Path::new(&self.sort_keys.2).extension()
+    }
+
+    // Rank of the entry according to its leading dot, plus the name normalized for comparison.
+    fn get_name_sort_keys(
+        base_name: &str,
+        sort_case: SortCase,
+        sort_dotfiles: SortDotfiles,
+    ) -> (u8, String) {
+        let is_dotfile = base_name.starts_with('.') && base_name.len() > 1;
+
+        let (dot_rank, name) = match sort_dotfiles {
+            SortDotfiles::First => (if is_dotfile { 0 } else { 1 }, base_name),
+            SortDotfiles::Last => (if is_dotfile { 1 } else { 0 }, base_name),
+            SortDotfiles::Mixed => (0, base_name.trim_start_matches('.')),
+        };
+
+        let name = match sort_case {
+            SortCase::Sensitive => name.to_string(),
+            SortCase::Insensitive => name.to_lowercase(),
+        };
+
+        (dot_rank, name)
+    }
+
+    fn get_size_and_unit(raw_info: &RawInfo) -> (String, String) {
+        // Directories only have a size when their contents were summed up
+        let size = match raw_info.dir_size {
+            Some(dir_size) if raw_info.is_directory => dir_size,
+            _ if raw_info.is_directory || raw_info.is_symlink => {
+                return (String::new(), String::new())
+            }
+            _ => raw_info.size,
+        };
+
+        Self::fmt_size_and_unit(size)
+    }
+
+    fn fmt_size_and_unit(size: u64) -> (String, String) {
+        if size < Self::KB {
+            (size.to_string(), "B".to_string())
+        } else if size < Self::MB {
+            ((size / Self::KB).to_string(), "K".to_string())
+        } else if size < Self::GB {
+            (format!("{:.1}", size as f64 / Self::MB as f64), "M".to_string())
+        } else {
+            (format!("{:.1}", size as f64 / Self::GB as f64), "G".to_string())
+        }
+    }
+
+    // The GECOS field may hold comma-separated extras (office, phone...), the name comes first.
+    fn gecos_full_name(gecos: &str) -> Option<String> {
+        let full_name = gecos.split(',').next().unwrap_or("").trim();
+        if full_name.is_empty() {
+            None
+        } else {
+            Some(full_name.to_string())
+        }
+    }
+}
+
+/// How names longer than the allowed length are shortened.
+#[derive(Default)]
+struct Truncation {
+    /// Maximum number of characters, 0 for no limit
+    max_length: usize,
+    mode: Truncate,
+    ellipsis: String,
+}
+
+impl Truncation {
+    // Counts characters rather than bytes, so multi-byte names are never cut mid-character.
+    fn apply(&self, string: &str) -> String {
+        let length = string.chars().count();
+        if self.max_length == 0 || length <= self.max_length {
+            return string.to_string();
+        }
+
+        let kept = self.max_length.saturating_sub(self.ellipsis.chars().count());
+        let head = |n| string.chars().take(n).collect::<String>();
+        let tail = |n| string.chars().skip(length - n).collect::<String>();
+        match self.mode {
+            Truncate::Middle => {
+                format!("{}{}{}", head(kept - kept / 2), self.ellipsis, tail(kept / 2))
+            }
+            Truncate::End => format!("{}{}", head(kept), self.ellipsis),
+            Truncate::Start => format!("{}{}", self.ellipsis, tail(kept)),
+        }
+    }
+}
+
+/// Settings shared by every row of a table.
+#[derive(Default)]
+struct TableStyle {
+    max_owner_colsize: usize,
+    file_colors: HashMap<String, String>,
+    user_groups: HashSet<u32>,
+    /// Whether cells keep their colors and styles
+    color: bool,
+    /// Colors the terminal supports, the others being replaced by the nearest ones
+    palette: Palette,
+    row_color: RowColor,
+    size_style: SizeStyle,
+    theme: Theme,
+    date_style: DateStyle,
+    /// Time dates are compared to
+    now: DateTime<Local>,
+    /// Age limit in days for the expires column
+    expires: Option<i64>,
+    /// Days before expiry from which certificates are highlighted, for the cert column
+    cert_expiry: Option<i64>,
+    /// Overlay mounts for the layer column
+    overlays: OverlayMounts,
+    /// Whether to count extended attributes for the xattrs column
+    count_xattrs: bool,
+}
+
+struct DisplayableInfo {
+    row_color: String,
+    permission_col: String,
+    links_col: String,
+    size_col: String,
+    owner_col: String,
+    date_col: String,
+    created_col: String,
+    accessed_col: String,
+    expires_col: String,
+    cert_col: String,
+    layer_col: String,
+    xattrs_col: String,
+    name_col: String,
+    is_main_dir: bool,
+}
+
+impl DisplayableInfo {
+    // ANSI color codes for zebra striping
+    const ZEBRA_EVEN: &'static str = "\x1b[48;5;236m"; // Dark gray background
+    const ZEBRA_ODD: &'static str = "\x1b[48;5;235m";  // Slightly darker gray background
+    const HIGHLIGHT: &'static str = "\x1b[48;5;24m";  // Blue background for highlighted rows
+
+    fn new(
+        row_index: usize,
+        processed_info: ProcessedInfo,
+        style: &TableStyle,
+        highlighted: bool,
+    ) -> Self {
+        let theme = &style.theme;
+        let now = style.now;
+        let age_color = Self::age_color(&processed_info, now, theme);
+
+        // Apply zebra striping (or the highlight), and the row's foreground color if any
+        let reset_color = format!(
+            "{}{}{}",
+            COLOR_RESET,
+            if highlighted {
+                Self::HIGHLIGHT
+            } else if row_index.is_multiple_of(2) {
+                Self::ZEBRA_EVEN
+            } else {
+                Self::ZEBRA_ODD
+            },
+            match style.row_color {
+                RowColor::None => "",
+                RowColor::Age => age_color,
+            }
+        );
+
+        let permission_col = Self::fmt_permissions(&processed_info, theme, &reset_color);
+        // Vanished entries have none to show
+        let links = processed_info.rinfo.links;
+        let links_col = match links {
+            0 => format!("{:>5}", ""),
+            links => format!("{:>5}", links),
+        };
+        let size_col = Self::fmt_size(&processed_info, style.size_style, theme, &reset_color);
+        let owner_col = Self::fmt_owner(
+            &processed_info,
+            style.max_owner_colsize,
+            &style.user_groups,
+            theme,
+            &reset_color,
+        );
+        let date_col = Self::fmt_modified_time(
+            &processed_info,
+            now,
+            &style.date_style,
+            age_color,
+            &reset_color,
+        );
+        let fmt_time = |time: Option<DateTime<Local>>| {
+            let color = time.map_or("", |time| Self::date_color(time, now, theme));
+            Self::fmt_date(time, now, &style.date_style, color, &reset_color)
+        };
+        let created_col = fmt_time(processed_info.rinfo.created_time);
+        let accessed_col = fmt_time(processed_info.rinfo.accessed_time);
+        let expires_col = match style.expires {
+            Some(max_days) => {
+                Self::fmt_expires(&processed_info, max_days, now, theme, &reset_color)
+            }
+            None => String::new(),
+        };
+        let cert_col = match style.cert_expiry {
+            Some(warning_days) => {
+                Self::fmt_cert(&processed_info, warning_days, now, theme, &reset_color)
+            }
+            None => String::new(),
+        };
+        let layer = style.overlays.layer(&processed_info.rinfo.path);
+        let layer_col = format!("{:>5}", layer.map(Layer::label).unwrap_or(""));
+        let xattrs = if style.count_xattrs {
+            xattr::count(&processed_info.rinfo.path).filter(|&count| count > 0)
+        } else {
+            None
+        };
+        let xattrs_col = format!("{:>5}", xattrs.map(|count| count.to_string()).unwrap_or_default());
+        let name_col = format!(
+            "{}{}",
+            Self::fmt_name(&processed_info, &style.file_colors, theme, &reset_color),
+            COLOR_RESET
+        );
+
+        let mut displayable_info = DisplayableInfo {
+            row_color: reset_color,
+            permission_col,
+            links_col,
+            size_col,
+            owner_col,
+            date_col,
+            created_col,
+            accessed_col,
+            expires_col,
+            cert_col,
+            layer_col,
+            xattrs_col,
+            name_col,
+            is_main_dir: processed_info.rinfo.is_main_dir,
+        };
+        if !style.color {
+            displayable_info.restyle(strip_ansi);
+        } else if style.palette == Palette::Basic {
+            displayable_info.restyle(|cell| style.palette.apply(cell));
+        }
+        displayable_info
+    }
+
+    // Rewrites the escape sequences of every cell (e.g. removing them), including those added
+    // along the way, such as diff markers.
+    fn restyle(&mut self, restyle: impl Fn(&str) -> String) {
+        for cell in [
+            &mut self.row_color,
+            &mut self.permission_col,
+            &mut self.links_col,
+            &mut self.size_col,
+            &mut self.owner_col,
+            &mut self.date_col,
+            &mut self.created_col,
+            &mut self.accessed_col,
+            &mut self.expires_col,
+            &mut self.cert_col,
+            &mut self.layer_col,
+            &mut self.xattrs_col,
+            &mut self.name_col,
+        ] {
+            *cell = restyle(cell);
+        }
+    }
+
+    fn column(&self, column: Column) -> &str {
+        match column {
+            Column::Perm => &self.permission_col,
+            Column::Links => &self.links_col,
+            Column::Size => &self.size_col,
+            Column::Owner => &self.owner_col,
+            Column::Modified => &self.date_col,
+            Column::Created => &self.created_col,
+            Column::Accessed => &self.accessed_col,
+            Column::Expires => &self.expires_col,
+            Column::Cert => &self.cert_col,
+            Column::Layer => &self.layer_col,
+            Column::Xattrs => &self.xattrs_col,
+            Column::Name => &self.name_col,
+        }
+    }
+
+    fn column_mut(&mut self, column: Column) -> &mut String {
+        match column {
+            Column::Perm => &mut self.permission_col,
+            Column::Links => &mut self.links_col,
+            Column::Size => &mut self.size_col,
+            Column::Owner => &mut self.owner_col,
+            Column::Modified => &mut self.date_col,
+            Column::Created => &mut self.created_col,
+            Column::Accessed => &mut self.accessed_col,
+            Column::Expires => &mut self.expires_col,
+            Column::Cert => &mut self.cert_col,
+            Column::Layer => &mut self.layer_col,
+            Column::Xattrs => &mut self.xattrs_col,
+            Column::Name => &mut self.name_col,
+        }
+    }
+
+    fn render(&self, columns: &[Column]) -> String {
+        let cols: Vec<&str> = columns.iter().map(|&column| self.column(column)).collect();
+        format!("{}{}", self.row_color, cols.join(" "))
+    }
+
+    fn render_template(&self, template: &Template) -> String {
+        let row = template.render(|column| self.column(column));
+        // Rows without colors (stripped along with the rest) have none to reset
+        if self.row_color.is_empty() {
+            row
+        } else {
+            format!("{}{}{}", self.row_color, row, COLOR_RESET)
+        }
+    }
+
+    // Each class of permissions in its color, those of the group and the others standing out
+    // when they can write (see WritableWarning). Setuid executables, running as their owner,
+    // stand out too.
+    fn fmt_permissions(pinfo: &ProcessedInfo, theme: &Theme, reset_color: &str) -> String {
+        let permissions = &pinfo.permissions;
+        let rinfo = &pinfo.rinfo;
+        let padding = " ".repeat(4usize.saturating_sub(permissions.len()));
+        // Octal digits after the special bits' one, or letters after the type ("d rwx r-x r-x")
+        let (prefix, classes) = match permissions.len() {
+            3 if !rinfo.vanished => permissions.split_at(0),
+            4 | 10 => permissions.split_at(1),
+            _ => return format!("{}{}{}", reset_color, padding, permissions),
+        };
+        let width = classes.len() / 3;
+        let setuid = rinfo.mode & 0o4000 != 0 && !rinfo.is_directory && rinfo.is_executable;
+        let (prefix_color, user_color) = match (setuid, permissions.len()) {
+            (true, 4) => (theme.setuid, theme.setuid),
+            (true, _) => ("", theme.setuid),
+            (false, _) => ("", theme.perm_user),
+        };
+        let sticky_dir = rinfo.is_directory && rinfo.mode & 0o1000 != 0;
+        let writable =
+            |bit: u32| rinfo.permissions() & bit != 0 && !rinfo.is_symlink && !sticky_dir;
+        let group_color = if writable(0o020) {
+            theme.group_writable
+        } else {
+            theme.perm_group
+        };
+        let other_color = if writable(0o002) {
+            theme.world_writable
+        } else {
+            theme.perm_other
+        };
+
+        let mut cell = format!("{}{}", reset_color, padding);
+        if !prefix_color.is_empty() {
+            cell.push_str(&format!("{}{}{}", prefix_color, prefix, reset_color));
+        } else {
+            cell.push_str(prefix);
+        }
+        for (i, color) in [user_color, group_color, other_color].iter().enumerate() {
+            let class = &classes[i * width..(i + 1) * width];
+            cell.push_str(&format!("{}{}{}", color, class, reset_color));
+        }
+        cell
+    }
+
+    fn fmt_size(
+        pinfo: &ProcessedInfo,
+        size_style: SizeStyle,
+        theme: &Theme,
+        reset_color: &str,
+    ) -> String {
+        if pinfo.size.is_empty() {
+            return match pinfo.rinfo.entry_counts {
+                Some(counts) => format!("{:>7}", format!("{}f/{}d", counts.files, counts.dirs)),
+                None => "      -".to_string(),
+            };
+        }
+
+        let unit_color = match pinfo.size_unit.as_str() {
+            "B" | "K" => theme.size_small,
+            "M" => theme.size_mega,
+            _ => theme.size_giga,
+        };
+
+        let unit = format!("{}{}{}", unit_color, pinfo.size_unit, reset_color);
+        match size_style {
+            SizeStyle::Compact => {
+                let size = format!("{}{}", pinfo.size_marker, pinfo.size);
+                format!("{:>6}{}", size, unit)
+            }
+            SizeStyle::Aligned => {
+                // Whole sizes leave the decimal places blank, so every unit is in the same place
+                let (integer, decimals) = match pinfo.size.split_once('.') {
+                    Some((integer, decimals)) => (integer, format!(".{}", decimals)),
+                    None => (pinfo.size.as_str(), String::new()),
+                };
+                let integer = format!("{}{}", pinfo.size_marker, integer);
+                format!("{:>4}{:<2}{}", integer, decimals, unit)
+            }
+        }
+    }
+
+    fn fmt_owner(
+        pinfo: &ProcessedInfo,
+        max_owner_colsize: usize,
+        user_groups: &HashSet<u32>,
+        theme: &Theme,
+        reset_color: &str,
+    ) -> String {
+        // Bold group when its permissions apply to the current user, dimmed otherwise.
+        let group_style = if user_groups.contains(&pinfo.rinfo.group_gid) {
+            theme.own_group
+        } else {
+            theme.other_group
+        };
+        let padding = max_owner_colsize
+            .saturating_sub(pinfo.username.len() + pinfo.groupname.len() + 1);
+
+        format!(
+            "{}:{}{}{}{}",
+            pinfo.username,
+            group_style,
+            pinfo.groupname,
+            reset_color,
+            " ".repeat(padding)
+        )
+    }
+
+    // Color of the modification date bucket, shared by the date cell and whole-row coloring.
+    // Dates in the future come from clock skew or touch -d, worth a warning.
+    fn age_color(pinfo: &ProcessedInfo, now: DateTime<Local>, theme: &Theme) -> &'static str {
+        pinfo.time.map_or("", |time| Self::date_color(time, now, theme))
+    }
+
+    fn date_color(date: DateTime<Local>, now: DateTime<Local>, theme: &Theme) -> &'static str {
+        let mdays = (now - date).num_days();
+
+        if date > now {
+            theme.date_future
+        } else if mdays > 30 {
+            theme.date_old
+        } else if mdays > 0 {
+            theme.date_recent
+        } else {
+            theme.date_today
+        }
+    }
+
+    fn fmt_modified_time(
+        pinfo: &ProcessedInfo,
+        now: DateTime<Local>,
+        date_style: &DateStyle,
+        color: &str,
+        reset_color: &str,
+    ) -> String {
+        Self::fmt_date(pinfo.time, now, date_style, color, reset_color)
+    }
+
+    // A date of the entry, or "-" if it's unknown
+    fn fmt_date(
+        date: Option<DateTime<Local>>,
+        now: DateTime<Local>,
+        date_style: &DateStyle,
+        color: &str,
+        reset_color: &str,
+    ) -> String {
+        let date = match date {
+            Some(date) => date,
+            None => return format!("{}{:>5} {}", color, "-", reset_color),
+        };
+
+        if *date_style == DateStyle::Relative {
+            return format!("{}{} {}", color, fmt_elapsed(now - date), reset_color);
+        }
+        let mdays = (now - date).num_days();
+
+        // Dates in the future are shown in full, as they are most likely mistakes
+        let (year_fmt, day_fmt, time_fmt) = date_style.formats();
+        let fmt = if mdays > 364 || date > now {
+            year_fmt
+        } else if mdays > 0 {
+            day_fmt
+        } else {
+            time_fmt
+        };
+
+        format!("{}{} {}", color, date.format(fmt), reset_color)
+    }
+
+    // Days left before the entry is older than max_days, negative once it is.
+    fn fmt_expires(
+        pinfo: &ProcessedInfo,
+        max_days: i64,
+        now: DateTime<Local>,
+        theme: &Theme,
+        reset_color: &str,
+    ) -> String {
+        let age_days = (now - pinfo.rinfo.modified_time).num_days();
+        let days_left = max_days - age_days;
+
+        let days = format!("{:>6}d", days_left);
+        if days_left < 0 {
+            format!("{}{}{}", theme.expired, days, reset_color)
+        } else {
+            days
+        }
+    }
+
+    // Days left before a certificate expires, highlighted within warning_days of it.
+    fn fmt_cert(
+        pinfo: &ProcessedInfo,
+        warning_days: i64,
+        now: DateTime<Local>,
+        theme: &Theme,
+        reset_color: &str,
+    ) -> String {
+        let rinfo = &pinfo.rinfo;
+        if rinfo.is_directory || rinfo.vanished {
+            return format!("{:>7}", "");
+        }
+        match cert::inspect(&rinfo.path) {
+            Some(cert::Contents::Certificate(expiry)) => {
+                let days_left = (expiry - now).num_days();
+                let days = format!("{:>6}d", days_left);
+                if days_left < warning_days {
+                    format!("{}{}{}", theme.expired, days, reset_color)
+                } else {
+                    days
+                }
+            }
+            Some(cert::Contents::Key) => format!("{:>7}", "key"),
+            Some(cert::Contents::Unknown) => format!("{:>7}", "?"),
+            None => format!("{:>7}", ""),
+        }
     }
 
     fn fmt_name(
         pinfo: &ProcessedInfo,
         file_colors: &HashMap<String, String>,
+        theme: &Theme,
+        reset_color: &str,
     ) -> String {
-        let mut fname = pinfo.name.clone();
+        // Use the original file name (without icons) for suffix checking
+        let original_name = pinfo
+            .rinfo
+            .path
+            .file_name()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_else(|| From::from("/"));
 
         // Apply green color to executable entries (except directories and folder links)
+        let mut name_color = String::new();
         if pinfo.is_executable {
-            fname = format!("{}{}{}", Self::GREEN, fname, COLOR_RESET);
+            name_color = theme.executable.to_string();
         } else if !file_colors.is_empty() {
             // Apply color to file names containing special suffixes
-            // Use the original file name (without icons) for suffix checking
-            let original_name = pinfo
-                .rinfo
-                .path
-                .file_name()
-                .map(|s| s.to_string_lossy())
-                .unwrap_or_else(|| From::from("/"));
             for (suffix, color) in file_colors {
                 if original_name.ends_with(suffix) {
-                    fname = format!("\x1b[{}{}{}", color, fname, COLOR_RESET);
+                    trace!("Colored {} by suffix {} ({})", original_name, suffix, color);
+                    name_color = format!("\x1b[{}", color);
                     break;
                 }
             }
         }
 
+        let mut fname =
+            Self::highlight_matches(pinfo, &original_name, theme.matched, &name_color, reset_color);
+        if !name_color.is_empty() {
+            fname = format!("{}{}{}", name_color, fname, reset_color);
+        }
+
         if !pinfo.target_name.is_empty() {
             fname = format!("{} -> {}", fname, pinfo.target_name);
         }
 
         fname
     }
+
+    // Paints the matched ranges of the file name, restoring the name color after each one.
+    // Names shortened by --max-name-length are left as they are, since the ranges no longer apply.
+    fn highlight_matches(
+        pinfo: &ProcessedInfo,
+        original_name: &str,
+        match_color: &str,
+        name_color: &str,
+        reset_color: &str,
+    ) -> String {
+        if pinfo.match_ranges.is_empty() || !pinfo.name.ends_with(original_name) {
+            return pinfo.name.clone();
+        }
+
+        let offset = pinfo.name.len() - original_name.len();
+        let mut highlighted = pinfo.name[..offset].to_string();
+        let mut last = 0;
+        for range in &pinfo.match_ranges {
+            highlighted.push_str(&original_name[last..range.start]);
+            highlighted.push_str(match_color);
+            highlighted.push_str(&original_name[range.clone()]);
+            highlighted.push_str(reset_color);
+            highlighted.push_str(name_color);
+            last = range.end;
+        }
+        highlighted.push_str(&original_name[last..]);
+        highlighted
+    }
 }
 
 fn get_file_info(path: &Path) -> Option<RawInfo> {
-    let metadata = match path.symlink_metadata() {
-        Ok(metadata) => metadata,
+    match path.symlink_metadata() {
+        Ok(metadata) => Some(raw_info_from(path, &metadata)),
         Err(e) => {
-            eprintln!("Error accessing {}: {}", path.display(), e);
-            return None;
+            diagnostics::report(EntryError::new(path, "Error accessing", &e));
+            None
         }
-    };
+    }
+}
 
+fn raw_info_from(path: &Path, metadata: &fs::Metadata) -> RawInfo {
     let modified_time = metadata
         .modified()
-        .ok()
-        .and_then(|time| {
-            let duration = time.duration_since(UNIX_EPOCH).ok()?;
-            DateTime::from_timestamp(duration.as_secs() as i64, 0)
-                .map(|dt| dt.with_timezone(&Local))
-        })
-        .unwrap_or_else(|| Local::now());
+        .map(DateTime::<Local>::from)
+        .unwrap_or_else(|_| Local::now());
 
-    Some(RawInfo {
+    RawInfo {
         path: path.to_path_buf(),
-        permissions: metadata.permissions().mode() & 0o777,
+        mode: metadata.mode(),
+        links: metadata.nlink(),
+        inode: metadata.ino(),
+        dev: metadata.dev(),
+        rdev: metadata.rdev(),
         size: metadata.len(),
         owner_uid: metadata.uid(),
         group_gid: metadata.gid(),
         modified_time,
+        created_time: metadata.created().ok().map(DateTime::<Local>::from),
+        accessed_time: metadata.accessed().ok().map(DateTime::<Local>::from),
+        changed_time: Local
+            .timestamp_opt(metadata.ctime(), metadata.ctime_nsec() as u32)
+            .single(),
         is_directory: metadata.is_dir(),
         is_executable: metadata.permissions().mode() & 0o100 != 0,
         is_symlink: metadata.file_type().is_symlink(),
         is_main_dir: false,
-    })
+        dir_size: None,
+        label: None,
+        depth: 0,
+        entry_counts: None,
+        vanished: false,
+    }
+}
+
+// Width of the terminal attached to stdout, if any. Falls back to $COLUMNS.
+fn terminal_width() -> Option<usize> {
+    let mut winsize: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut winsize) };
+    if rc == 0 && winsize.ws_col > 0 {
+        return Some(winsize.ws_col as usize);
+    }
+
+    env::var("COLUMNS").ok().and_then(|c| c.parse().ok())
+}
+
+// Number of terminal cells a string takes, ignoring ANSI escape sequences.
+fn visible_width(string: &str) -> usize {
+    strip_ansi(string).chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+// The string without its ANSI escape sequences.
+fn strip_ansi(string: &str) -> String {
+    let mut stripped = String::with_capacity(string.len());
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the final byte of the sequence (e.g. the 'm' in "\x1b[0m")
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+// Columns to display, dropping the lowest priority ones until every row fits in the terminal.
+fn fit_columns(
+    displayable_infos: &[DisplayableInfo],
+    max_owner_colsize: usize,
+    widths: &HashMap<Column, usize>,
+    mut columns: Vec<Column>,
+    priority: &[Column],
+    term_width: Option<usize>,
+) -> Vec<Column> {
+    let term_width = match term_width {
+        Some(width) => width,
+        None => return columns,
+    };
+
+    let col_width = |column: Column| {
+        displayable_infos
+            .iter()
+            .map(|dinfo| visible_width(dinfo.column(column)))
+            .chain(std::iter::once(column.header(max_owner_colsize, widths).len()))
+            .max()
+            .unwrap_or(0)
+    };
+    let widths: HashMap<Column, usize> = columns.iter().map(|&c| (c, col_width(c))).collect();
+
+    // Columns missing from the priority list are the first ones to go
+    let mut drop_order: Vec<Column> = columns
+        .iter()
+        .filter(|c| !priority.contains(c))
+        .chain(priority.iter().rev().filter(|c| columns.contains(c)))
+        .copied()
+        .filter(|&c| c != Column::Name)
+        .collect();
+    drop_order.dedup();
+
+    let row_width = |columns: &[Column]| {
+        columns.iter().map(|c| widths[c]).sum::<usize>() + columns.len().saturating_sub(1)
+    };
+
+    for column in drop_order {
+        if row_width(&columns) <= term_width {
+            break;
+        }
+        columns.retain(|&c| c != column);
+    }
+
+    columns
+}
+
+// Effective and supplementary groups of the running process.
+fn get_process_groups() -> HashSet<u32> {
+    let mut groups = HashSet::new();
+    groups.insert(users::get_effective_gid());
+
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count > 0 {
+        let mut buf: Vec<libc::gid_t> = vec![0; count as usize];
+        let count = unsafe { libc::getgroups(count, buf.as_mut_ptr()) };
+        if count > 0 {
+            groups.extend(&buf[..count as usize]);
+        }
+    }
+
+    groups
+}
+
+// The users crate doesn't expose the GECOS field, so it's read straight from the passwd entry.
+fn get_user_gecos(uid: u32) -> Option<String> {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() || pwd.pw_gecos.is_null() {
+        return None;
+    }
+
+    let gecos = unsafe { std::ffi::CStr::from_ptr(pwd.pw_gecos) };
+    Some(gecos.to_string_lossy().to_string())
+}
+
+// Counts a directory's entries without reading their metadata. None if it can't be read.
+fn count_entries(directory: &Path) -> Option<EntryCounts> {
+    let entries = fs::read_dir(directory).ok()?;
+    let mut counts = EntryCounts { files: 0, dirs: 0 };
+
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            counts.dirs += 1;
+        } else {
+            counts.files += 1;
+        }
+    }
+
+    Some(counts)
+}
+
+// Number with its thousands separated by commas, e.g. "1,204".
+fn fmt_thousands(number: u64) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// Hidden the way the platform's file manager sees it: names starting with a dot or listed in
+// the directory's .hidden file, plus entries flagged as hidden on macOS and Windows.
+fn is_hidden(entry: &fs::DirEntry, hidden_names: &HashSet<String>) -> bool {
+    let file_name = entry.file_name().to_string_lossy().to_string();
+    file_name.starts_with('.') || hidden_names.contains(&file_name) || has_hidden_attribute(entry)
+}
+
+// Names listed (one per line) in a directory's .hidden file, as in GNOME/Nautilus.
+fn read_hidden_file(directory: &Path) -> HashSet<String> {
+    fs::read_to_string(directory.join(".hidden"))
+        .map(|content| {
+            content
+                .lines()
+                .map(|line| line.trim_end_matches('/').to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+fn has_hidden_attribute(entry: &fs::DirEntry) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    entry
+        .metadata()
+        .is_ok_and(|m| m.st_flags() & libc::UF_HIDDEN != 0)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(entry: &fs::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    entry
+        .metadata()
+        .is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
 }
 
-fn list_directory(directory: &Path, show_hidden: bool) -> Vec<RawInfo> {
+#[cfg(not(any(target_os = "macos", windows)))]
+fn has_hidden_attribute(_entry: &fs::DirEntry) -> bool {
+    false
+}
+
+fn list_directory(directory: &Path, scan: &ScanOptions) -> Vec<RawInfo> {
     let mut raw_infos = Vec::new();
 
     let entries = match fs::read_dir(directory) {
         Ok(entries) => entries,
         Err(e) => {
-            eprintln!("Permission denied: {}: {}", directory.display(), e);
+            diagnostics::report(EntryError::new(directory, "Cannot read directory", &e));
             return raw_infos;
         }
     };
 
+    let hidden_names = if scan.show_hidden {
+        HashSet::new()
+    } else {
+        read_hidden_file(directory)
+    };
+
     for entry in entries {
         let entry = match entry {
             Ok(entry) => entry,
             Err(e) => {
-                eprintln!("Error reading directory entry: {}", e);
+                diagnostics::report(EntryError::new(directory, "Error reading entry in", &e));
                 continue;
             }
         };
 
         let path = entry.path();
-        let file_name = path
-            .file_name()
-            .map(|s| s.to_string_lossy())
-            .unwrap_or(From::from(""));
 
-        if show_hidden || !file_name.starts_with('.') {
-            if let Some(raw_info) = get_file_info(&path) {
-                raw_infos.push(raw_info);
+        if !scan.show_hidden && is_hidden(&entry, &hidden_names) {
+            debug!("Skipped {}: hidden", path.display());
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !scan.accepts(&path, &file_name) {
+            continue;
+        }
+
+        // Busy directories (spools, tmp) may lose entries between read_dir and their stat.
+        // A second attempt catches files being replaced, the others are gone for good.
+        let metadata = match path.symlink_metadata() {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => path.symlink_metadata(),
+            result => result,
+        };
+        match metadata {
+            Ok(metadata) => raw_infos.push(raw_info_from(&path, &metadata)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!("Skipped {}: vanished while listing", path.display());
+                if scan.show_vanished {
+                    raw_infos.push(RawInfo::vanished(&path));
+                }
             }
+            Err(e) => diagnostics::report(EntryError::new(&path, "Error accessing", &e)),
         }
     }
 
@@ -550,28 +3369,281 @@ fn list_directory(directory: &Path, show_hidden: bool) -> Vec<RawInfo> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+
+    /// A directory of the system temp dir for a test, removed with its contents when dropped,
+    /// even if the test panics.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(format!("myls-{}-{}", name, process::id()));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = PathBuf;
+
+        fn deref(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
 
-    fn mock_raw_info(path: &str, size: u64, is_directory: bool) -> RawInfo {
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    pub(crate) fn mock_raw_info(path: &str, size: u64, is_directory: bool) -> RawInfo {
         RawInfo {
             path: PathBuf::from(path),
-            permissions: 0o755,
+            mode: if is_directory { 0o040755 } else { 0o100755 },
+            links: 1,
+            inode: 0,
+            dev: 0,
+            rdev: 0,
             size,
             owner_uid: 1000,
             group_gid: 1000,
             modified_time: Local::now(),
+            created_time: None,
+            accessed_time: None,
+            changed_time: None,
             is_directory,
             is_executable: false,
             is_symlink: false,
             is_main_dir: false,
+            dir_size: None,
+            label: None,
+            depth: 0,
+            entry_counts: None,
+            vanished: false,
+        }
+    }
+
+    fn mock_processed_info(raw_info: RawInfo) -> ProcessedInfo {
+        ProcessedInfo::new(
+            raw_info,
+            &Icons::default(),
+            &Truncation::default(),
+            OwnerNames::Login,
+            false,
+            SortCase::Insensitive,
+            SortDotfiles::First,
+        )
+    }
+
+    fn mock_style() -> TableStyle {
+        TableStyle {
+            max_owner_colsize: 20,
+            color: true,
+            now: Local::now(),
+            ..Default::default()
         }
     }
 
+    fn mock_displayable_info(row_index: usize, processed: ProcessedInfo) -> DisplayableInfo {
+        DisplayableInfo::new(row_index, processed, &mock_style(), false)
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = Template::parse("{name}").unwrap();
+        let processed = || mock_processed_info(mock_raw_info("/tmp/file", 0, false));
+        let colored = mock_displayable_info(0, processed());
+        assert!(colored.render_template(&template).ends_with(COLOR_RESET));
+
+        let style = TableStyle {
+            color: false,
+            ..mock_style()
+        };
+        let plain = DisplayableInfo::new(0, processed(), &style, false);
+        assert_eq!(plain.render_template(&template), "file");
+    }
+
     #[test]
     fn test_process_root_path() {
         let raw_info = mock_raw_info("/", 0, true);
-        let processed = ProcessedInfo::new(raw_info, false, 0);
+        let processed = mock_processed_info(raw_info);
         assert_eq!(processed.name, "■ /");
-        assert_eq!(processed.sort_keys, (1, "/".to_string()));
+        assert_eq!(processed.sort_keys, (1, 1, "/".to_string()));
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let file = |path, size, age_days| {
+            let mut raw_info = mock_raw_info(path, size, false);
+            raw_info.modified_time = Local::now() - chrono::Duration::days(age_days);
+            mock_processed_info(raw_info)
+        };
+        let mut processed = [
+            file("/tmp/b.txt", 300, 2),
+            file("/tmp/a.rs", 100, 1),
+            file("/tmp/c", 200, 3),
+            mock_processed_info(mock_raw_info("/tmp/b-dir", 0, true)),
+        ];
+        let mut sorted = |order| -> Vec<String> {
+            processed.sort_by(|a, b| a.compare(b, order));
+            processed.iter().map(|p| p.name.clone()).collect()
+        };
+        let mut names = |by, reverse| {
+            let group_dirs = GroupDirs::First;
+            sorted(SortOrder { by, reverse, group_dirs })
+        };
+
+        assert_eq!(names(SortBy::Name, false), vec!["■ b-dir", "a.rs", "b.txt", "c"]);
+        assert_eq!(names(SortBy::Size, false), vec!["■ b-dir", "b.txt", "c", "a.rs"]);
+        assert_eq!(names(SortBy::Time, false), vec!["■ b-dir", "a.rs", "b.txt", "c"]);
+        assert_eq!(names(SortBy::Ext, false), vec!["■ b-dir", "c", "a.rs", "b.txt"]);
+        assert_eq!(names(SortBy::Name, true), vec!["■ b-dir", "c", "b.txt", "a.rs"]);
+        assert_eq!(names(SortBy::Size, true), vec!["■ b-dir", "a.rs", "c", "b.txt"]);
+
+        let by = SortBy::Name;
+        let reverse = false;
+        let order = SortOrder { by, reverse, group_dirs: GroupDirs::Last };
+        assert_eq!(sorted(order), vec!["a.rs", "b.txt", "c", "■ b-dir"]);
+        let order = SortOrder { by, reverse, group_dirs: GroupDirs::None };
+        assert_eq!(sorted(order), vec!["a.rs", "■ b-dir", "b.txt", "c"]);
+    }
+
+    #[test]
+    fn test_sort_ties() {
+        let entry = |path, inode| {
+            let mut raw_info = mock_raw_info(path, 0, false);
+            raw_info.inode = inode;
+            mock_processed_info(raw_info)
+        };
+        let order = SortOrder { by: SortBy::Name, reverse: false, group_dirs: GroupDirs::First };
+        let expected = [("/tmp/README", 1), ("/tmp/Readme", 9), ("/tmp/Readme", 12)];
+        for mut processed in [
+            [entry("/tmp/Readme", 12), entry("/tmp/README", 1), entry("/tmp/Readme", 9)],
+            [entry("/tmp/Readme", 9), entry("/tmp/Readme", 12), entry("/tmp/README", 1)],
+        ] {
+            processed.sort_by(|a, b| a.compare(b, order));
+            let sorted: Vec<(&str, u64)> = processed
+                .iter()
+                .map(|p| (p.rinfo.path.to_str().unwrap(), p.rinfo.inode))
+                .collect();
+            assert_eq!(sorted, expected);
+        }
+    }
+
+    #[test]
+    fn test_sort_by_owner() {
+        let owned = |path, username: &str, groupname: &str| {
+            let mut processed = mock_processed_info(mock_raw_info(path, 0, false));
+            processed.username = username.to_string();
+            processed.groupname = groupname.to_string();
+            processed
+        };
+        let mut processed = [
+            owned("/tmp/a", "zoe", "staff"),
+            owned("/tmp/b", "ana", "wheel"),
+            owned("/tmp/c", "zoe", "audio"),
+        ];
+        let mut names = |by| -> Vec<String> {
+            let order = SortOrder { by, reverse: false, group_dirs: GroupDirs::First };
+            processed.sort_by(|a, b| a.compare(b, order));
+            processed.iter().map(|p| p.name.clone()).collect()
+        };
+
+        assert_eq!(names(SortBy::Owner), vec!["b", "a", "c"]);
+        assert_eq!(names(SortBy::Group), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_name_sort_keys() {
+        let keys = |name, case, dotfiles| ProcessedInfo::get_name_sort_keys(name, case, dotfiles);
+
+        assert_eq!(keys("Makefile", SortCase::Insensitive, SortDotfiles::First), (1, "makefile".to_string()));
+        assert_eq!(keys("Makefile", SortCase::Sensitive, SortDotfiles::First), (1, "Makefile".to_string()));
+        assert_eq!(keys(".bashrc", SortCase::Insensitive, SortDotfiles::First), (0, ".bashrc".to_string()));
+        assert_eq!(keys(".bashrc", SortCase::Insensitive, SortDotfiles::Last), (1, ".bashrc".to_string()));
+        assert_eq!(keys(".bashrc", SortCase::Insensitive, SortDotfiles::Mixed), (0, "bashrc".to_string()));
+    }
+
+    #[test]
+    fn test_sort_by_depth() {
+        let dir = TempDir::new("depth");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::write(dir.join("a/b/deep"), "").unwrap();
+        fs::write(dir.join("a/mid"), "").unwrap();
+        fs::write(dir.join("z"), "").unwrap();
+
+        let args =
+            Args::try_parse_from(["myls", "--sort", "depth", "--group-dirs", "none"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        let paths = [dir.to_string_lossy().to_string()];
+        let source = source::for_paths(&paths).unwrap();
+        let raw_infos =
+            scan_recursive(std::slice::from_ref(&*dir), &settings.scan, source.as_ref()).unwrap();
+        let names: Vec<String> = settings
+            .process_sorted(raw_infos)
+            .into_iter()
+            .skip(1)
+            .map(|pinfo| strip_ansi(&pinfo.name))
+            .collect();
+        assert_eq!(names, ["■ a", "z", "■ a/b", "a/mid", "a/b/deep"]);
+    }
+
+    #[test]
+    fn test_recursive_match() {
+        let dir = TempDir::new("recursive-match");
+        fs::create_dir_all(dir.join("src/sub")).unwrap();
+        fs::create_dir_all(dir.join("tests.rs")).unwrap();
+        for name in ["a.rs", "notes.md", "src/b.rs", "src/sub/c.rs", "src/sub/d.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let args = Args::try_parse_from(["myls", "-R", "--match", "*.rs"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        let paths = [dir.to_string_lossy().to_string()];
+        let source = source::for_paths(&paths).unwrap();
+        let raw_infos =
+            scan_recursive(std::slice::from_ref(&*dir), &settings.scan, source.as_ref()).unwrap();
+        let mut labels: Vec<String> = raw_infos
+            .iter()
+            .filter(|raw_info| !raw_info.is_main_dir)
+            .map(|raw_info| raw_info.label.clone().unwrap_or_else(|| file_name(raw_info)))
+            .collect();
+        labels.sort();
+        assert_eq!(labels, ["a.rs", "src/b.rs", "src/sub/c.rs", "tests.rs"]);
+    }
+
+    #[test]
+    fn test_expand_directories() {
+        let dir = TempDir::new("expand");
+        fs::create_dir_all(dir.join("open")).unwrap();
+        fs::create_dir_all(dir.join("closed")).unwrap();
+        fs::write(dir.join("open/a"), "").unwrap();
+        fs::write(dir.join("open/b"), "").unwrap();
+        fs::write(dir.join("closed/c"), "").unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+
+        let args = Args::try_parse_from(["myls", "--expand", "open"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        let paths = [dir.to_string_lossy().to_string()];
+        let source = source::for_paths(&paths).unwrap();
+        let raw_infos =
+            scan_paths(std::slice::from_ref(&*dir), &settings.scan, source.as_ref()).unwrap();
+        let processed_infos = settings.process_sorted(raw_infos);
+        let names: Vec<String> =
+            expand_directories(processed_infos, &args.list.expand, &settings, source.as_ref())
+                .into_iter()
+                .skip(1)
+                .map(|pinfo| strip_ansi(&pinfo.name))
+                .collect();
+        assert_eq!(names, ["■ closed", "■ open", "├── a", "└── b", "file"]);
+        assert!(Args::try_parse_from(["myls", "--expand", "open", "-R"]).is_err());
     }
 
     #[test]
@@ -599,11 +3671,59 @@ mod tests {
         assert_eq!(unit, "G");
     }
 
+    #[test]
+    fn test_numeric_ids() {
+        let args = Args::try_parse_from(["myls", "-n"]).unwrap();
+        let owner_names = OwnerNames::new(&args.list.display);
+        assert_eq!(owner_names.owner(4242), "4242");
+        assert_eq!(owner_names.group(0), "0");
+        let mut raw_info = mock_raw_info("/tmp/file", 0, false);
+        raw_info.owner_uid = 0;
+        let pinfo = ProcessedInfo::new(
+            raw_info,
+            &Icons::default(),
+            &Truncation::default(),
+            owner_names,
+            false,
+            SortCase::Insensitive,
+            SortDotfiles::First,
+        );
+        assert_eq!((pinfo.username.as_str(), pinfo.groupname.as_str()), ("0", "1000"));
+
+        // -n keeps counting files in recent
+        assert!(Args::try_parse_from(["myls", "recent", "-n", "5"]).is_ok());
+        assert!(Args::try_parse_from(["myls", "-n", "--owner-full-name"]).is_err());
+    }
+
+    #[test]
+    fn test_owner_aliases() {
+        let config = Config::parse("[owner_aliases]\n\"domain users\" = \"du\"").unwrap();
+        let args = Args::try_parse_from(["myls"]).unwrap();
+        let settings = Settings::new(&args.list.display, config);
+        let alias = |name: &str| {
+            let mut name = name.to_string();
+            settings.owner_alias(&mut name);
+            name
+        };
+        assert_eq!(alias("domain users"), "du");
+        assert_eq!(alias("root"), "root");
+    }
+
+    #[test]
+    fn test_gecos_full_name() {
+        assert_eq!(
+            ProcessedInfo::gecos_full_name("Jane Doe,Room 42,555-1234,"),
+            Some("Jane Doe".to_string())
+        );
+        assert_eq!(ProcessedInfo::gecos_full_name(""), None);
+        assert_eq!(ProcessedInfo::gecos_full_name(",,,"), None);
+    }
+
     #[test]
     fn test_displayable_info_formatting() {
         let raw_info = mock_raw_info("/tmp/file.txt", 1234, false);
-        let processed = ProcessedInfo::new(raw_info, false, 0);
-        let displayable = DisplayableInfo::new(0, processed, 20, &HashMap::new());
+        let processed = mock_processed_info(raw_info);
+        let displayable = mock_displayable_info(0, processed);
 
         // Test zebra striping (even row)
         assert!(displayable.permission_col.contains(DisplayableInfo::ZEBRA_EVEN));
@@ -611,22 +3731,706 @@ mod tests {
         assert!(displayable.date_col.contains(DisplayableInfo::ZEBRA_EVEN));
 
         let raw_info_odd = mock_raw_info("/tmp/file2.txt", 5678, false);
-        let processed_odd = ProcessedInfo::new(raw_info_odd, false, 0);
-        let displayable_odd = DisplayableInfo::new(1, processed_odd, 20, &HashMap::new());
+        let processed_odd = mock_processed_info(raw_info_odd);
+        let displayable_odd = mock_displayable_info(1, processed_odd);
 
         // Test zebra striping (odd row)
         assert!(displayable_odd.permission_col.contains(DisplayableInfo::ZEBRA_ODD));
     }
 
+    #[test]
+    fn test_group_membership_hint() {
+        let mut user_groups = HashSet::new();
+        user_groups.insert(1000);
+
+        let processed = mock_processed_info(mock_raw_info("/tmp/mine", 1, false));
+        let style = TableStyle {
+            user_groups,
+            ..mock_style()
+        };
+        let displayable = DisplayableInfo::new(0, processed, &style, false);
+        assert!(displayable.owner_col.contains(Theme::DEFAULT.own_group));
+
+        let processed = mock_processed_info(mock_raw_info("/tmp/other", 1, false));
+        let displayable = mock_displayable_info(0, processed);
+        assert!(displayable.owner_col.contains(Theme::DEFAULT.other_group));
+    }
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("\x1b[48;5;236m 755\x1b[0m"), 4);
+        assert_eq!(visible_width("📁 src"), 6);
+    }
+
+    #[test]
+    fn test_cut_entries() {
+        let mut main_dir = mock_raw_info("/tmp", 0, true);
+        main_dir.is_main_dir = true;
+        let mut processed = vec![mock_processed_info(main_dir)];
+        for name in ["/tmp/a", "/tmp/b", "/tmp/c"] {
+            processed.push(mock_processed_info(mock_raw_info(name, 0, false)));
+        }
+        assert_eq!(cut_entries(&mut processed, None), 0);
+        assert_eq!(cut_entries(&mut processed, Some(5)), 0);
+        assert_eq!(cut_entries(&mut processed, Some(1)), 2);
+        let paths: Vec<&Path> = processed.iter().map(|p| p.rinfo.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/tmp"), Path::new("/tmp/a")]);
+
+        let args = Args::try_parse_from(["myls", "--max-entries", "1", "--all-entries"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        assert_eq!(settings.max_entries, None);
+    }
+
+    #[test]
+    fn test_common_owner() {
+        let owned = |path, username: &str| {
+            let mut processed = mock_processed_info(mock_raw_info(path, 0, false));
+            processed.username = username.to_string();
+            processed.groupname = "staff".to_string();
+            processed
+        };
+        let mut processed = vec![owned("/tmp/a", "alice"), owned("/tmp/b", "alice")];
+        assert_eq!(common_owner(&processed).as_deref(), Some("alice:staff"));
+
+        let mut vanished = owned("/tmp/c", "?");
+        vanished.rinfo.vanished = true;
+        processed.push(vanished);
+        assert_eq!(common_owner(&processed).as_deref(), Some("alice:staff"));
+
+        processed.push(owned("/tmp/d", "bob"));
+        assert_eq!(common_owner(&processed), None);
+        assert_eq!(common_owner(&[]), None);
+    }
+
+    #[test]
+    fn test_fit_columns() {
+        let processed = mock_processed_info(mock_raw_info("/tmp/file.txt", 100, false));
+        let displayable = mock_displayable_info(0, processed);
+        let displayables = [displayable];
+        let priority = [Column::Name, Column::Size, Column::Modified, Column::Perm, Column::Owner];
+
+        let fit = |width| {
+            fit_columns(&displayables, 20, &HashMap::new(), Column::ALL.to_vec(), &priority, width)
+        };
+
+        assert_eq!(fit(None), Column::ALL.to_vec());
+        assert_eq!(fit(Some(30)), vec![Column::Size, Column::Modified, Column::Name]);
+        assert_eq!(fit(Some(1)), vec![Column::Name]);
+    }
+
+    #[test]
+    fn test_entry_counts() {
+        let dir = TempDir::new("counts");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+
+        let counts = count_entries(&dir);
+        assert_eq!(counts, Some(EntryCounts { files: 1, dirs: 1 }));
+
+        let mut raw_info = mock_raw_info("/tmp/dir", 0, true);
+        raw_info.entry_counts = counts;
+        let displayable = mock_displayable_info(0, mock_processed_info(raw_info));
+        assert_eq!(displayable.size_col, "  1f/1d");
+    }
+
+    #[test]
+    fn test_target_counts() {
+        let dir = TempDir::new("target-counts");
+        fs::create_dir_all(dir.join("data/sub")).unwrap();
+        fs::write(dir.join("data/file"), "").unwrap();
+        std::os::unix::fs::symlink(dir.join("data"), dir.join("link")).unwrap();
+
+        let args = Args::try_parse_from(["myls", "--target-counts"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        let link = dir.join("link");
+        let raw_info = raw_info_from(&link, &link.symlink_metadata().unwrap());
+        let processed = settings.process(raw_info);
+        assert!(processed.target_name.ends_with("data (2 items)"));
+        let remote = RawInfo {
+            is_symlink: true,
+            ..mock_raw_info("sftp://host/link", 0, false)
+        };
+        assert!(remote.is_remote());
+        assert!(!settings.process(remote).target_name.contains("items"));
+
+        assert_eq!(fmt_thousands(1204), "1,204");
+        assert_eq!(fmt_thousands(999), "999");
+        assert_eq!(fmt_thousands(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn test_dedup_paths() {
+        let dir = TempDir::new("dedup");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("file"), "").unwrap();
+        std::os::unix::fs::symlink(dir.join("file"), dir.join("link")).unwrap();
+
+        let paths = vec![
+            dir.join("file"),
+            dir.join("sub/../file"),
+            dir.join("link"),
+            dir.join("sub"),
+            dir.join("./sub"),
+            PathBuf::from("missing"),
+            PathBuf::from("missing"),
+        ];
+        let unique = dedup_paths(paths);
+        let expected = [dir.join("file"), dir.join("link"), dir.join("sub"), "missing".into()];
+        assert_eq!(unique, expected);
+    }
+
+    #[test]
+    fn test_date_order() {
+        let now = Local::now();
+        assert_eq!(DateOrder::from_locale("en_US.UTF-8"), DateOrder::Mdy);
+        assert_eq!(DateOrder::from_locale("pt_BR.UTF-8"), DateOrder::Dmy);
+        assert_eq!(DateOrder::from_locale("ja_JP.UTF-8"), DateOrder::Ymd);
+        assert_eq!(DateOrder::from_locale("C"), DateOrder::Dmy);
+
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.modified_time = Local.with_ymd_and_hms(2001, 2, 3, 4, 5, 6).unwrap();
+        let processed = mock_processed_info(raw_info);
+        let date = |order| {
+            DisplayableInfo::fmt_modified_time(&processed, now, &DateStyle::Adaptive(order), "", "")
+        };
+        assert_eq!(date(DateOrder::Dmy), "03/02/2001 ");
+        assert_eq!(date(DateOrder::Mdy), "02/03/2001 ");
+        assert_eq!(date(DateOrder::Ymd), "2001-02-03 ");
+
+        let custom = DateStyle::Custom("%b %e %Y %H:%M".to_string());
+        let date = DisplayableInfo::fmt_modified_time(&processed, now, &custom, "", "");
+        assert_eq!(date, "Feb  3 2001 04:05 ");
+        assert!(parse_date_format("%Y-%m-%d %H:%M").is_ok());
+        assert!(parse_date_format("%Y-%Q").is_err());
+    }
+
+    #[test]
+    fn test_relative_time() {
+        let now = Local::now();
+        let elapsed = |seconds| fmt_elapsed(chrono::Duration::seconds(seconds));
+        assert_eq!(elapsed(42), "42s ago");
+        assert_eq!(elapsed(3 * 3600 + 59), "3h ago");
+        assert_eq!(elapsed(2 * 86400), "2d ago");
+        assert_eq!(elapsed(160 * 86400), "5mo ago");
+        assert_eq!(elapsed(800 * 86400), "2y ago");
+        assert_eq!(elapsed(-3 * 86400), "in 3d");
+
+        // The age colors still apply
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.modified_time = now - chrono::Duration::days(400);
+        let old = mock_processed_info(raw_info);
+        let color = DisplayableInfo::age_color(&old, now, &Theme::DEFAULT);
+        let date = DisplayableInfo::fmt_modified_time(&old, now, &DateStyle::Relative, color, "");
+        assert_eq!(date, format!("{}1y ago ", Theme::DEFAULT.date_old));
+    }
+
+    #[test]
+    fn test_size_style() {
+        let size = |bytes, size_style| {
+            let processed = mock_processed_info(mock_raw_info("/tmp/file", bytes, false));
+            let cell = DisplayableInfo::fmt_size(&processed, size_style, &Theme::DEFAULT, "");
+            strip_ansi(&cell)
+        };
+        let mega = 3 * 1024 * 1024 / 2;
+        assert_eq!(size(1023, SizeStyle::Compact), "  1023B");
+        assert_eq!(size(mega, SizeStyle::Compact), "   1.5M");
+        assert_eq!(size(1023, SizeStyle::Aligned), "1023  B");
+        assert_eq!(size(mega, SizeStyle::Aligned), "   1.5M");
+        assert_eq!(size(512 * 1024, SizeStyle::Aligned), " 512  K");
+    }
+
+    #[test]
+    fn test_columns() {
+        let settings = |args: &[&str]| {
+            let args = Args::try_parse_from([&["myls"], args].concat()).unwrap();
+            Settings::new(&args.list.display, Config::default())
+        };
+        let shown = |settings: Settings| {
+            Column::ALL.into_iter().filter(|&c| settings.shows(c)).collect::<Vec<_>>()
+        };
+
+        let narrow = settings(&["--columns", "name,size,perm"]);
+        assert_eq!(shown(narrow), vec![Column::Perm, Column::Size, Column::Name]);
+        let created = settings(&["--columns", "perm,created,name"]);
+        assert_eq!(shown(created), vec![Column::Perm, Column::Created, Column::Name]);
+        assert_eq!(shown(settings(&[])).len(), 5);
+        assert!(Args::try_parse_from(["myls", "--columns", "perm,nope"]).is_err());
+    }
+
+    #[test]
+    fn test_perm_style() {
+        assert_eq!(fmt_symbolic(0o040755), "drwxr-xr-x");
+        assert_eq!(fmt_symbolic(0o100644), "-rw-r--r--");
+        assert_eq!(fmt_symbolic(0o120777), "lrwxrwxrwx");
+        assert_eq!(fmt_symbolic(0o104755), "-rwsr-xr-x");
+        assert_eq!(fmt_symbolic(0o102644), "-rw-r-Sr--");
+        assert_eq!(fmt_symbolic(0o041777), "drwxrwxrwt");
+        assert_eq!(fmt_symbolic(0o010600), "prw-------");
+
+        let mut displayable = mock_displayable_info(0, {
+            let mut processed = mock_processed_info(mock_raw_info("/tmp/dir", 0, true));
+            processed.permissions = fmt_symbolic(processed.rinfo.mode);
+            processed
+        });
+        let widths = HashMap::from([(Column::Perm, 10)]);
+        assert_eq!(strip_ansi(displayable.column_mut(Column::Perm)), "drwxr-xr-x");
+        assert_eq!(Column::Perm.header(0, &widths), "      PERM");
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let args = Args::try_parse_from(["myls", "--deterministic", "2024-03-01 12:00"]).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        assert_eq!(settings.now.format("%Y-%m-%d %H:%M").to_string(), "2024-03-01 12:00");
+        assert!(!settings.color);
+        assert_eq!(settings.date_style, DateStyle::Adaptive(DateOrder::Dmy));
+        assert_eq!(settings.separator.width, SeparatorWidth::Fixed(60));
+
+        let mut raw_info = mock_raw_info("/tmp/file", 100, false);
+        raw_info.modified_time = Local.with_ymd_and_hms(2024, 2, 20, 8, 30, 0).unwrap();
+        let processed = settings.process(raw_info);
+        let date_style = &settings.date_style;
+        let date = DisplayableInfo::fmt_modified_time(&processed, settings.now, date_style, "", "");
+        assert_eq!(date, "20/02 ");
+
+        // Durations count back from the frozen time, not the clock
+        let args = ["myls", "--deterministic", "2024-03-01 12:00", "--newer-than", "2d"];
+        let args = Args::try_parse_from(args).unwrap();
+        let settings = Settings::new(&args.list.display, Config::default());
+        let mut raw_info = mock_raw_info("/tmp/file", 100, false);
+        raw_info.modified_time = Local.with_ymd_and_hms(2024, 2, 28, 13, 0, 0).unwrap();
+        assert!(settings.keep(&raw_info));
+        raw_info.modified_time = Local.with_ymd_and_hms(2024, 2, 27, 12, 0, 0).unwrap();
+        assert!(!settings.keep(&raw_info));
+
+        assert_eq!(parse_now("@0"), Ok(Local.timestamp_opt(0, 0).unwrap()));
+        assert!(parse_now("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_perm_colors() {
+        let theme = Theme::DEFAULT;
+        let cell_with = |mode: u32, is_directory, theme: &Theme| {
+            let mut raw_info = mock_raw_info("/tmp/entry", 0, is_directory);
+            raw_info.mode = mode;
+            let processed = mock_processed_info(raw_info);
+            DisplayableInfo::fmt_permissions(&processed, theme, "")
+        };
+        let cell = |mode, is_directory| cell_with(mode, is_directory, &theme);
+        assert_eq!(
+            cell(0o100644, false),
+            format!(" {}6{}4{}4", theme.perm_user, theme.perm_group, theme.perm_other)
+        );
+        assert!(cell(0o100666, false).contains(&format!("{}6", theme.world_writable)));
+        assert!(!cell(0o041777, true).contains(theme.world_writable));
+        assert!(cell(0o100664, false).contains(&format!("{}6", theme.group_writable)));
+
+        let world = WritableWarning::World.apply(theme);
+        assert!(!cell_with(0o100664, false, &world).contains(theme.group_writable));
+        let none = WritableWarning::None.apply(theme);
+        assert!(!cell_with(0o100666, false, &none).contains(theme.world_writable));
+
+        let mut processed = mock_processed_info(mock_raw_info("/tmp/dir", 0, true));
+        processed.permissions = fmt_symbolic(processed.rinfo.mode);
+        let symbolic = DisplayableInfo::fmt_permissions(&processed, &theme, "");
+        assert_eq!(strip_ansi(&symbolic), "drwxr-xr-x");
+        assert!(symbolic.starts_with(&format!("d{}rwx", theme.perm_user)));
+    }
+
+    #[test]
+    fn test_special_bits() {
+        let theme = Theme::DEFAULT;
+        let mut raw_info = mock_raw_info("/usr/bin/passwd", 100, false);
+        raw_info.mode = 0o104755;
+        raw_info.is_executable = true;
+        let processed = mock_processed_info(raw_info);
+        assert_eq!(processed.permissions, "4755");
+        let cell = DisplayableInfo::fmt_permissions(&processed, &theme, "");
+        assert_eq!(strip_ansi(&cell), "4755");
+        assert!(cell.starts_with(&format!("{}4{}7", theme.setuid, theme.setuid)));
+
+        // Setgid directories only make new entries inherit their group
+        let mut raw_info = mock_raw_info("/srv/shared", 0, true);
+        raw_info.mode = 0o042775;
+        let processed = mock_processed_info(raw_info);
+        assert_eq!(processed.permissions, "2775");
+        let cell = DisplayableInfo::fmt_permissions(&processed, &theme, "");
+        assert!(!cell.contains(theme.setuid));
+        assert_eq!(fmt_symbolic(0o042775), "drwxrwsr-x");
+    }
+
+    #[test]
+    fn test_icons() {
+        let icons = Icons {
+            enabled: true,
+            hidden: vec![IconKind::Target],
+            ..Icons::default()
+        };
+        assert_eq!(icons.prefix(IconKind::Dir, true, "dir".to_string()), "📂 dir");
+        assert_eq!(icons.prefix(IconKind::Dir, false, "sub".to_string()), "📁 sub");
+        assert_eq!(icons.prefix(IconKind::Target, false, "real".to_string()), "real");
+
+        let plain = Icons {
+            placeholder: String::new(),
+            ..Icons::default()
+        };
+        assert_eq!(plain.prefix(IconKind::Dir, false, "sub".to_string()), "sub");
+        let args = ["myls", "--no-icons-for", "dir,target", "--icon-placeholder", "▸"];
+        assert!(Args::try_parse_from(args).is_ok());
+    }
+
+    #[test]
+    fn test_time_field() {
+        let now = Local::now();
+        let file = |path, accessed_days_ago: Option<i64>| {
+            let mut raw_info = mock_raw_info(path, 100, false);
+            raw_info.accessed_time = accessed_days_ago
+                .map(|days| now - chrono::Duration::days(days));
+            let mut processed = mock_processed_info(raw_info);
+            processed.time = processed.rinfo.time(TimeField::Atime);
+            processed
+        };
+        let mut processed = [
+            file("/tmp/a", Some(3)),
+            file("/tmp/b", None),
+            file("/tmp/c", Some(1)),
+        ];
+        let order = SortOrder { by: SortBy::Time, reverse: false, group_dirs: GroupDirs::First };
+        processed.sort_by(|a, b| a.compare(b, order));
+        let names: Vec<&str> = processed.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["c", "a", "b"]);
+
+        // Unknown times are shown as such
+        let date_style = DateStyle::default();
+        let date = DisplayableInfo::fmt_modified_time(&processed[2], now, &date_style, "", "");
+        assert_eq!(date, "    - ");
+        assert!(Args::try_parse_from(["myls", "--time", "status"]).is_ok());
+    }
+
+    #[test]
+    fn test_created_accessed() {
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.accessed_time = Some(Local.with_ymd_and_hms(2001, 2, 3, 4, 5, 6).unwrap());
+        let displayable = mock_displayable_info(0, mock_processed_info(raw_info));
+        assert_eq!(strip_ansi(&displayable.accessed_col), "03/02/2001 ");
+        // Birth times aren't recorded everywhere
+        assert_eq!(strip_ansi(&displayable.created_col), "    - ");
+
+        let widths = HashMap::from([(Column::Created, 17)]);
+        assert_eq!(Column::Created.header(0, &widths), "         CREATED ");
+        assert_eq!(Column::Accessed.header(0, &widths), "ACCESSED ");
+    }
+
+    #[test]
+    fn test_time_style() {
+        let now = Local::now();
+        let style = |extra: &[&str]| {
+            let args = Args::parse_from([&["myls"], extra].concat());
+            DateStyle::new(&args.list.display)
+        };
+        assert_eq!(style(&["--time-style", "iso"]), DateStyle::Iso);
+        assert_eq!(
+            style(&["--time-style", "long-iso"]),
+            DateStyle::Custom("%Y-%m-%d %H:%M".to_string())
+        );
+        let conflicting = ["myls", "--time-style", "iso", "--date-format", "%F"];
+        assert!(Args::try_parse_from(conflicting).is_err());
+
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.modified_time = now - chrono::Duration::days(2);
+        let recent = mock_processed_info(raw_info);
+        let expected = recent.rinfo.modified_time.format("%m-%d %H:%M ").to_string();
+        let date = DisplayableInfo::fmt_modified_time(&recent, now, &DateStyle::Iso, "", "");
+        assert_eq!(date, expected);
+    }
+
+    #[test]
+    fn test_expires() {
+        let now = Local::now();
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.modified_time = now - chrono::Duration::days(10);
+        let processed = mock_processed_info(raw_info);
+
+        let days_left = DisplayableInfo::fmt_expires(&processed, 30, now, &Theme::DEFAULT, "");
+        assert_eq!(days_left, "    20d");
+        let expired = DisplayableInfo::fmt_expires(&processed, 7, now, &Theme::DEFAULT, "");
+        assert_eq!(expired, format!("{}    -3d", Theme::DEFAULT.expired));
+    }
+
+    #[test]
+    fn test_future_date() {
+        let now = Local::now();
+        let mut raw_info = mock_raw_info("/tmp/file.txt", 100, false);
+        raw_info.modified_time = now + chrono::Duration::days(3);
+        let processed = mock_processed_info(raw_info);
+
+        let age_color = DisplayableInfo::age_color(&processed, now, &Theme::DEFAULT);
+        assert_eq!(age_color, Theme::DEFAULT.date_future);
+        let date_style = DateStyle::default();
+        let date = DisplayableInfo::fmt_modified_time(&processed, now, &date_style, "", "");
+        assert_eq!(date.len(), "2001-02-03 ".len());
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = TempDir::new("glob");
+        fs::write(dir.join("b.log"), "").unwrap();
+        fs::write(dir.join("a.log"), "").unwrap();
+        fs::write(dir.join("c.txt"), "").unwrap();
+
+        let pattern = dir.join("*.log").display().to_string();
+        assert_eq!(expand_glob(&pattern), vec![dir.join("a.log"), dir.join("b.log")]);
+        let missing = dir.join("*.iso").display().to_string();
+        assert_eq!(expand_glob(&missing), vec![PathBuf::from(&missing)]);
+        assert_eq!(expand_glob("plain"), vec![PathBuf::from("plain")]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut main_dir = mock_raw_info("/tmp", 4096, true);
+        main_dir.is_main_dir = true;
+        let processed = vec![
+            mock_processed_info(main_dir),
+            mock_processed_info(mock_raw_info("/tmp/a.iso", 1024 * 1024, false)),
+            mock_processed_info(mock_raw_info("/tmp/b.iso", 1024 * 1024, false)),
+            mock_processed_info(mock_raw_info("/tmp/dir", 4096, true)),
+        ];
+        assert_eq!(fmt_summary(&processed).unwrap(), "2 files, 1 dir, 2.0M");
+        assert_eq!(fmt_summary(&processed[3..]).unwrap(), "0 files, 1 dir, 0B");
+        assert_eq!(fmt_summary(&processed[..1]), None);
+    }
+
+    #[test]
+    fn test_separator() {
+        assert_eq!(parse_separator_width("auto"), Ok(SeparatorWidth::Auto));
+        assert_eq!(parse_separator_width("20"), Ok(SeparatorWidth::Fixed(20)));
+        assert!(parse_separator_width("wide").is_err());
+
+        let separator = Separator {
+            character: '═',
+            width: SeparatorWidth::Fixed(3),
+            color: Some("38;5;240m".to_string()),
+            palette: Palette::Extended,
+        };
+        assert_eq!(separator.render(), format!("\x1b[38;5;240m═══{}", COLOR_RESET));
+        let separator = Separator {
+            palette: Palette::Basic,
+            ..separator
+        };
+        assert_eq!(separator.render(), format!("\x1b[90m═══{}", COLOR_RESET));
+    }
+
+    #[test]
+    fn test_truncation() {
+        let truncate = |mode| Truncation {
+            max_length: 8,
+            mode,
+            ellipsis: "…".to_string(),
+        };
+        assert_eq!(truncate(Truncate::Middle).apply("report-2024.pdf"), "repo…pdf");
+        assert_eq!(truncate(Truncate::End).apply("report-2024.pdf"), "report-…");
+        assert_eq!(truncate(Truncate::Start).apply("report-2024.pdf"), "…024.pdf");
+        assert_eq!(truncate(Truncate::Middle).apply("short"), "short");
+        assert_eq!(truncate(Truncate::End).apply("ünïcödé_ñämé"), "ünïcödé…");
+        assert_eq!(Truncation::default().apply("report-2024.pdf"), "report-2024.pdf");
+    }
+
+    #[test]
+    fn test_vanished() {
+        let processed = mock_processed_info(RawInfo::vanished(Path::new("/var/spool/job-42")));
+        assert_eq!(processed.name, "job-42 (vanished)");
+
+        let displayable = mock_displayable_info(0, processed);
+        assert!(displayable.permission_col.ends_with(" ---"));
+        assert_eq!(displayable.size_col, "      -");
+        assert!(displayable.date_col.contains("    - "));
+    }
+
+    #[test]
+    fn test_main_dir_link() {
+        let dir = TempDir::new("dir-link");
+        fs::create_dir_all(dir.join("real")).unwrap();
+        fs::write(dir.join("real/file"), "").unwrap();
+        std::os::unix::fs::symlink("real", dir.join("link")).unwrap();
+
+        let raw_infos = scan_paths(&[dir.join("link")], &ScanOptions::default(), &source::LocalFs).unwrap();
+        assert_eq!(raw_infos.len(), 2);
+        assert!(raw_infos[0].is_main_dir && raw_infos[0].is_symlink);
+
+        let processed = mock_processed_info(raw_infos.into_iter().next().unwrap());
+        assert_eq!(processed.target_name, "■ real");
+        assert!(!processed.is_executable);
+    }
+
+    #[test]
+    fn test_link_size() {
+        let dir = TempDir::new("link-size");
+        fs::write(dir.join("file"), "12345").unwrap();
+        std::os::unix::fs::symlink(dir.join("file"), dir.join("link")).unwrap();
+
+        let process = |link_size| {
+            let mut raw_info = mock_raw_info(dir.join("link").to_str().unwrap(), 0, false);
+            raw_info.is_symlink = true;
+            ProcessedInfo::new(
+                raw_info,
+                &Icons::default(),
+                &Truncation::default(),
+                OwnerNames::Login,
+                link_size,
+                SortCase::Insensitive,
+                SortDotfiles::First,
+            )
+        };
+        assert_eq!(process(false).size, "");
+        let displayable = mock_displayable_info(0, process(true));
+        assert!(displayable.size_col.starts_with("    →5"));
+    }
+
+    #[test]
+    fn test_hidden_file() {
+        let dir = TempDir::new("hidden");
+        fs::write(dir.join(".hidden"), "secret\nsnap/\n").unwrap();
+        fs::write(dir.join("secret"), "").unwrap();
+        fs::write(dir.join("visible"), "").unwrap();
+        fs::write(dir.join("cache.pyc"), "").unwrap();
+
+        let names = |show_hidden| -> Vec<String> {
+            let scan = ScanOptions {
+                show_hidden,
+                ignore_patterns: vec![Pattern::new("*.pyc").unwrap()],
+                ..Default::default()
+            };
+            let mut names: Vec<String> = list_directory(&dir, &scan)
+                .iter()
+                .map(|r| r.path.file_name().unwrap().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(false), vec!["visible"]);
+        assert_eq!(names(true), vec![".hidden", "secret", "visible"]);
+    }
+
+    #[test]
+    fn test_match_patterns() {
+        let dir = TempDir::new("match");
+        for name in ["main.rs", "lib.rs", "README.md", "Cargo.toml"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let scan = ScanOptions {
+            match_patterns: vec![Pattern::new("*.rs").unwrap(), Pattern::new("*.md").unwrap()],
+            ..Default::default()
+        };
+        let mut names: Vec<String> = list_directory(&dir, &scan)
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["README.md", "lib.rs", "main.rs"]);
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let dir = TempDir::new("exclude");
+        fs::create_dir_all(dir.join("target")).unwrap();
+        for name in ["main.rs", "main.o", ".cache.o"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let scan = ScanOptions {
+            show_hidden: true,
+            exclude_patterns: vec![Pattern::new("target").unwrap(), Pattern::new("*.o").unwrap()],
+            ..Default::default()
+        };
+        let names: Vec<String> = list_directory(&dir, &scan)
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["main.rs"]);
+    }
+
+    #[test]
+    fn test_row_color_age() {
+        let processed = mock_processed_info(mock_raw_info("/tmp/file.txt", 100, false));
+        let style = TableStyle {
+            row_color: RowColor::Age,
+            ..mock_style()
+        };
+        let displayable = DisplayableInfo::new(0, processed, &style, false);
+
+        // Modified now, so every cell of the row gets today's color
+        assert!(displayable.row_color.ends_with(Theme::DEFAULT.date_today));
+        assert!(displayable.size_col.ends_with(Theme::DEFAULT.date_today));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let processed = mock_processed_info(mock_raw_info("/tmp/file.txt", 100, false));
+        let displayable = DisplayableInfo::new(0, processed, &mock_style(), true);
+
+        assert!(displayable.row_color.contains(DisplayableInfo::HIGHLIGHT));
+        assert!(!displayable.row_color.contains(DisplayableInfo::ZEBRA_EVEN));
+    }
+
     #[test]
     fn test_file_colors() {
         let mut file_colors = HashMap::new();
         file_colors.insert(".txt".to_string(), "31m".to_string()); // Red
 
         let raw_info = mock_raw_info("/tmp/file.txt", 100, false);
-        let processed = ProcessedInfo::new(raw_info, false, 0);
-        let displayable = DisplayableInfo::new(0, processed, 20, &file_colors);
+        let processed = mock_processed_info(raw_info);
+        let style = TableStyle {
+            file_colors,
+            ..mock_style()
+        };
+        let displayable = DisplayableInfo::new(0, processed, &style, false);
 
         assert!(displayable.name_col.contains("\x1b[31m"));
     }
+
+    #[test]
+    fn test_links() {
+        let dir = TempDir::new("links");
+        let file = dir.join("file");
+        fs::write(&file, "").unwrap();
+        let hard_link = dir.join("file.2");
+        fs::hard_link(&file, &hard_link).unwrap();
+
+        let raw_info = raw_info_from(&file, &file.symlink_metadata().unwrap());
+        assert_eq!(raw_info.links, 2);
+        let displayable = mock_displayable_info(0, mock_processed_info(raw_info));
+        assert_eq!(displayable.column(Column::Links), "    2");
+        assert_eq!(Column::Links.header(0, &HashMap::new()), "LINKS");
+    }
+
+    #[test]
+    fn test_no_color() {
+        let mut processed = mock_processed_info(mock_raw_info("/tmp/run.sh", 100, false));
+        processed.is_executable = true;
+        let style = TableStyle {
+            color: false,
+            ..mock_style()
+        };
+        let displayable = DisplayableInfo::new(0, processed, &style, true);
+
+        let row = displayable.render(&Column::ALL);
+        assert!(!row.contains('\x1b'));
+        assert!(row.ends_with("run.sh"));
+    }
+
+    #[test]
+    fn test_match_highlight() {
+        let raw_info = mock_raw_info("/tmp/main.rs", 100, false);
+        let mut processed = mock_processed_info(raw_info);
+        processed.match_ranges = vec![0..2, 4..7];
+        let (m, r) = (Theme::DEFAULT.matched, COLOR_RESET);
+        let name = DisplayableInfo::highlight_matches(&processed, "main.rs", m, "", r);
+        assert_eq!(name, format!("{m}ma{r}in{m}.rs{r}"));
+
+        // Shortened names are not highlighted
+        processed.name = "mai…".to_string();
+        let name = DisplayableInfo::highlight_matches(&processed, "main.rs", m, "", r);
+        assert_eq!(name, "mai…");
+    }
 }
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::filesystem::unescape;
+
+/// Layer of an overlay filesystem (e.g. a container's root) an entry comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    /// Created or modified since the mount, in the writable layer
+    Upper,
+    /// Untouched, as found in the read-only (image) layers
+    Lower,
+    /// On an overlay whose upper directory isn't visible from here
+    Unknown,
+}
+
+impl Layer {
+    pub fn label(self) -> &'static str {
+        match self {
+            Layer::Upper => "upper",
+            Layer::Lower => "lower",
+            Layer::Unknown => "?",
+        }
+    }
+}
+
+struct OverlayMount {
+    mount_point: PathBuf,
+    upper_dir: PathBuf,
+}
+
+/// Overlay mounts of the current process, read from `/proc/self/mountinfo`.
+#[derive(Default)]
+pub struct OverlayMounts {
+    mounts: Vec<OverlayMount>,
+}
+
+impl OverlayMounts {
+    pub fn load() -> Self {
+        match fs::read_to_string("/proc/self/mountinfo") {
+            Ok(mountinfo) => Self::parse(&mountinfo),
+            Err(e) => {
+                debug!("Could not read mount points: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    // Lines look like "36 35 0:52 / /merged rw shared:1 - overlay overlay rw,lowerdir=...,upperdir=..."
+    fn parse(mountinfo: &str) -> Self {
+        let mut mounts = Vec::new();
+        for line in mountinfo.lines() {
+            let (mount_fields, fs_fields) = match line.split_once(" - ") {
+                Some(fields) => fields,
+                None => continue,
+            };
+            let mut fs_fields = fs_fields.split(' ');
+            if fs_fields.next() != Some("overlay") {
+                continue;
+            }
+
+            let mount_point = mount_fields.split(' ').nth(4);
+            let upper_dir = fs_fields
+                .nth(1)
+                .and_then(|options| options.split(',').find_map(|o| o.strip_prefix("upperdir=")));
+            if let (Some(mount_point), Some(upper_dir)) = (mount_point, upper_dir) {
+                debug!("Overlay mounted on {} with upper layer {}", mount_point, upper_dir);
+                mounts.push(OverlayMount {
+                    mount_point: PathBuf::from(unescape(mount_point)),
+                    upper_dir: PathBuf::from(unescape(upper_dir)),
+                });
+            }
+        }
+        OverlayMounts { mounts }
+    }
+
+    /// Layer the entry comes from, or None if it's not on an overlay.
+    pub fn layer(&self, path: &Path) -> Option<Layer> {
+        if self.mounts.is_empty() {
+            return None;
+        }
+
+        // The entry itself may be a link, only its directory is resolved
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).ok()?.join(name),
+            _ => fs::canonicalize(path).ok()?,
+        };
+        let mount = self
+            .mounts
+            .iter()
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.as_os_str().len())?;
+
+        if !mount.upper_dir.is_dir() {
+            return Some(Layer::Unknown);
+        }
+        let relative_path = path.strip_prefix(&mount.mount_point).ok()?;
+        if mount.upper_dir.join(relative_path).symlink_metadata().is_ok() {
+            Some(Layer::Upper)
+        } else {
+            Some(Layer::Lower)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer() {
+        let dir = crate::tests::TempDir::new("overlay");
+        fs::create_dir_all(dir.join("merged")).unwrap();
+        fs::create_dir_all(dir.join("upper dir")).unwrap();
+        fs::write(dir.join("merged/changed"), "").unwrap();
+        fs::write(dir.join("merged/original"), "").unwrap();
+        fs::write(dir.join("upper dir/changed"), "").unwrap();
+
+        let dir = fs::canonicalize(&dir).unwrap();
+        let mountinfo = format!(
+            "22 1 8:1 / / rw - ext4 /dev/sda1 rw\n\
+             36 22 0:52 / {} rw shared:1 - overlay overlay rw,lowerdir=/l1:/l2,upperdir={},workdir=/w\n",
+            dir.join("merged").display(),
+            dir.join("upper dir").display().to_string().replace(' ', "\\040"),
+        );
+        let overlays = OverlayMounts::parse(&mountinfo);
+
+        assert_eq!(overlays.layer(&dir.join("merged/changed")), Some(Layer::Upper));
+        assert_eq!(overlays.layer(&dir.join("merged/original")), Some(Layer::Lower));
+        assert_eq!(overlays.layer(&dir.join("upper dir/changed")), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::report::{self, Row};
+use crate::{OutputFormat, OwnerNames, ProcessedInfo, RawInfo, Settings};
+
+/// Number and combined size of the files belonging to one user.
+#[derive(Debug, PartialEq, Serialize)]
+struct OwnerUsage {
+    owner: String,
+    files: u64,
+    size: u64,
+}
+
+/// Prints one row per owner of the given files, biggest users first.
+/// Directories are left out, only the files inside them are counted.
+pub fn print(raw_infos: &[RawInfo], settings: &Settings) -> i32 {
+    let mut usages = aggregate(raw_infos, settings.owner_names);
+    if settings.output == OutputFormat::Table {
+        for usage in &mut usages {
+            settings.owner_alias(&mut usage.owner);
+        }
+    }
+    report::print(&usages, settings, |usages| print_table(usages, settings))
+}
+
+impl Row for OwnerUsage {
+    const FIELDS: &'static [&'static str] = &["owner", "files", "size"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.owner.clone(), self.files.to_string(), self.size.to_string()]
+    }
+}
+
+fn aggregate(raw_infos: &[RawInfo], owner_names: OwnerNames) -> Vec<OwnerUsage> {
+    let mut by_uid: HashMap<u32, (u64, u64)> = HashMap::new();
+    for raw_info in raw_infos.iter().filter(|r| !r.is_directory) {
+        let (files, size) = by_uid.entry(raw_info.owner_uid).or_default();
+        *files += 1;
+        *size += raw_info.size;
+    }
+
+    let mut usages: Vec<OwnerUsage> = by_uid
+        .into_iter()
+        .map(|(uid, (files, size))| OwnerUsage {
+            owner: owner_names.owner(uid),
+            files,
+            size,
+        })
+        .collect();
+    usages.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.owner.cmp(&b.owner)));
+    usages
+}
+
+fn print_table(usages: &[OwnerUsage], settings: &Settings) {
+    let owner_colsize = usages.iter().map(|u| u.owner.len()).max().unwrap_or(0).max(5);
+
+    let header = format!("{:<width$}   FILES    SIZE", "OWNER", width = owner_colsize);
+    println!("{}", settings.header(&header));
+    for usage in usages {
+        let (size, unit) = ProcessedInfo::fmt_size_and_unit(usage.size);
+        println!(
+            "{:<width$} {:>7} {:>6}{}",
+            usage.owner,
+            usage.files,
+            size,
+            unit,
+            width = owner_colsize
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let raw_info = |uid, size, is_directory| RawInfo {
+            owner_uid: uid,
+            size,
+            is_directory,
+            ..crate::tests::mock_raw_info("/tmp/file", 0, false)
+        };
+        let raw_infos = [
+            raw_info(4242, 10, false),
+            raw_info(4243, 100, false),
+            raw_info(4242, 20, false),
+            raw_info(4243, 4096, true),
+        ];
+        let usages = aggregate(&raw_infos, OwnerNames::Login);
+
+        assert_eq!(usages.len(), 2);
+        assert_eq!((usages[0].files, usages[0].size), (1, 100));
+        assert_eq!((usages[1].files, usages[1].size), (2, 30));
+
+        let usages = aggregate(&raw_infos[..1], OwnerNames::Numeric);
+        assert_eq!(usages[0].owner, "4242");
+    }
+}
@@ -0,0 +1,56 @@
+use std::cmp::Reverse;
+use std::path::Path;
+
+use crate::{list_directory, print_listing, DisplayArgs, RawInfo, Settings};
+
+#[derive(clap::Args)]
+// -n stands for the number of files here, as with head and tail
+#[command(mut_arg("numeric_ids", |arg| arg.short(None)))]
+pub struct RecentArgs {
+    /// Directory to search (default: current directory)
+    #[arg(default_value = ".")]
+    path: String,
+
+    /// Number of files to list
+    #[arg(short = 'n', long, default_value = "20")]
+    count: usize,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+pub fn run(args: &RecentArgs, settings: &Settings) -> i32 {
+    let root = Path::new(&args.path);
+    if !root.is_dir() {
+        eprintln!("Error: {} is not a directory", root.display());
+        return 1;
+    }
+
+    let mut files = Vec::new();
+    collect_files(root, root, settings, &mut files);
+
+    // Newest first, the oldest of them first with --reverse
+    files.sort_by_key(|raw_info| Reverse(raw_info.modified_time));
+    files.truncate(args.count);
+    if settings.sort_order.reverse {
+        files.reverse();
+    }
+    let processed_infos = files.into_iter().map(|r| settings.process(r)).collect();
+
+    print_listing(processed_infos, settings)
+}
+
+// Gathers every file under the directory, labeled with its path from the root.
+fn collect_files(root: &Path, directory: &Path, settings: &Settings, files: &mut Vec<RawInfo>) {
+    for mut raw_info in list_directory(directory, &settings.scan) {
+        if raw_info.is_directory {
+            if !raw_info.is_symlink {
+                collect_files(root, &raw_info.path, settings, files);
+            }
+        } else if settings.keep(&raw_info) {
+            let relative_path = raw_info.path.strip_prefix(root).unwrap_or(&raw_info.path);
+            raw_info.label = Some(relative_path.display().to_string());
+            files.push(raw_info);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::report::{self, Row};
+use crate::{
+    list_directory, print_listing, DisplayArgs, OutputFormat, ProcessedInfo, Settings,
+    COLOR_RESET,
+};
+
+#[derive(clap::Args)]
+pub struct RenamePreviewArgs {
+    /// Substitution applied to each name, as in sed: s/PATTERN/REPLACEMENT/FLAGS.
+    /// PATTERN is a regular expression, REPLACEMENT may refer to it with & and \1 to \9,
+    /// FLAGS may be g (replace every match) and i (ignore case)
+    expression: String,
+
+    /// Directory whose entries would be renamed (default: current directory)
+    #[arg(default_value = ".")]
+    path: String,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+/// What an entry would be renamed to. Unchanged entries keep their name as new name.
+#[derive(Debug, PartialEq, Serialize)]
+struct Rename {
+    name: String,
+    new_name: String,
+    /// Another entry has or would get the new name, one overwriting the other
+    conflict: bool,
+}
+
+impl Row for Rename {
+    const FIELDS: &'static [&'static str] = &["name", "new_name", "conflict"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.name.clone(), self.new_name.clone(), self.conflict.to_string()]
+    }
+}
+
+/// A sed-like substitution on file names.
+struct Substitution {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl Substitution {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let mut chars = expression.chars();
+        if chars.next() != Some('s') {
+            return Err("expected s/PATTERN/REPLACEMENT/".to_string());
+        }
+        let delimiter = chars.next().ok_or("missing delimiter after s")?;
+
+        // Escapes are kept for the regex and the replacement, except for escaped delimiters
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            let part = parts.last_mut().unwrap();
+            if escaped {
+                if c != delimiter {
+                    part.push('\\');
+                }
+                part.push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == delimiter {
+                parts.push(String::new());
+            } else {
+                part.push(c);
+            }
+        }
+        if parts.len() != 3 {
+            return Err("expected s/PATTERN/REPLACEMENT/".to_string());
+        }
+
+        let mut global = false;
+        let mut ignore_case = false;
+        for flag in parts[2].chars() {
+            match flag {
+                'g' => global = true,
+                'i' => ignore_case = true,
+                _ => return Err(format!("unknown flag {}", flag)),
+            }
+        }
+
+        let regex = RegexBuilder::new(&parts[0])
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Substitution {
+            regex,
+            replacement: sed_replacement(&parts[1]),
+            global,
+        })
+    }
+
+    fn apply(&self, name: &str) -> String {
+        if self.global {
+            self.regex.replace_all(name, self.replacement.as_str()).into_owned()
+        } else {
+            self.regex.replace(name, self.replacement.as_str()).into_owned()
+        }
+    }
+}
+
+// Translates sed's replacement syntax (& and \1) into the regex crate's (${0} and ${1}).
+fn sed_replacement(replacement: &str) -> String {
+    let mut translated = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => translated.push_str("${0}"),
+            '$' => translated.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit) if digit.is_ascii_digit() => {
+                    translated.push_str(&format!("${{{}}}", digit))
+                }
+                Some('$') => translated.push_str("$$"),
+                Some(other) => translated.push(other),
+                None => translated.push('\\'),
+            },
+            c => translated.push(c),
+        }
+    }
+    translated
+}
+
+pub fn run(args: &RenamePreviewArgs, settings: &Settings) -> i32 {
+    let substitution = match Substitution::parse(&args.expression) {
+        Ok(substitution) => substitution,
+        Err(e) => {
+            eprintln!("Error: invalid expression {}: {}", args.expression, e);
+            return 1;
+        }
+    };
+
+    let directory = Path::new(&args.path);
+    if !directory.is_dir() {
+        eprintln!("Error: {} is not a directory", directory.display());
+        return 1;
+    }
+
+    let raw_infos = list_directory(directory, &settings.scan)
+        .into_iter()
+        .filter(|raw_info| settings.keep(raw_info))
+        .collect();
+    let mut rows = settings.process_sorted(raw_infos);
+
+    // Every name in the directory counts for conflicts, hidden and filtered out ones included
+    let existing: HashSet<String> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().to_string()))
+            .collect(),
+        Err(_) => HashSet::new(),
+    };
+    let names = rows.iter().map(file_name).collect();
+    let renames = preview(names, &existing, &substitution);
+
+    if settings.output != OutputFormat::Table {
+        return report::print(&renames, settings, |_| ());
+    }
+    for (pinfo, rename) in rows.iter_mut().zip(&renames) {
+        if rename.new_name == rename.name {
+            continue;
+        }
+        let theme = &settings.theme;
+        let (color, note) = if rename.conflict {
+            (theme.conflict, " (conflict)")
+        } else {
+            (theme.added, "")
+        };
+        pinfo.name = format!(
+            "{}{}{} → {}{}{}{}",
+            theme.removed, pinfo.name, COLOR_RESET, color, rename.new_name, note, COLOR_RESET
+        );
+    }
+
+    print_listing(rows, settings)
+}
+
+// New names of the listed entries, conflicting when shared by several of them or when taken
+// by an entry of the directory that isn't renamed.
+fn preview(
+    names: Vec<String>,
+    existing: &HashSet<String>,
+    substitution: &Substitution,
+) -> Vec<Rename> {
+    let new_names: Vec<String> = names.iter().map(|name| substitution.apply(name)).collect();
+
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for new_name in &new_names {
+        *name_counts.entry(new_name).or_default() += 1;
+    }
+    let renamed: HashSet<&str> = names
+        .iter()
+        .zip(&new_names)
+        .filter(|(name, new_name)| name != new_name)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let taken = |new_name: &str| existing.contains(new_name) && !renamed.contains(new_name);
+
+    names
+        .iter()
+        .zip(&new_names)
+        .map(|(name, new_name)| Rename {
+            name: name.clone(),
+            new_name: new_name.clone(),
+            conflict: name != new_name && (name_counts[new_name.as_str()] > 1 || taken(new_name)),
+        })
+        .collect()
+}
+
+fn file_name(pinfo: &ProcessedInfo) -> String {
+    pinfo
+        .rinfo
+        .path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitution() {
+        let apply = |expression, name| Substitution::parse(expression).unwrap().apply(name);
+        assert_eq!(apply("s/jpeg$/jpg/", "photo.jpeg"), "photo.jpg");
+        assert_eq!(apply("s/ /_/g", "my holiday pic.png"), "my_holiday_pic.png");
+        assert_eq!(apply("s/ /_/", "my holiday pic.png"), "my_holiday pic.png");
+        assert_eq!(apply(r"s/(\d+)-(\w+)/\2-\1/", "01-intro.md"), "intro-01.md");
+        assert_eq!(apply("s/readme/[&]/i", "README.md"), "[README].md");
+        assert_eq!(apply(r"s|a/b|\$|", "a/b"), "$");
+        assert_eq!(apply(r"s,\,,;,g", "a,b,c"), "a;b;c");
+
+        assert!(Substitution::parse("y/a/b/").is_err());
+        assert!(Substitution::parse("s/a/b").is_err());
+        assert!(Substitution::parse("s/a/b/x").is_err());
+        assert!(Substitution::parse("s/(/b/").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let substitution = Substitution::parse("s/jpeg$/jpg/").unwrap();
+        let names = ["a.jpeg", "b.jpeg", "c.jpeg", "c.jpg"];
+        let names = names.iter().map(|name| name.to_string()).collect();
+        // b.jpg is there, only filtered out of the listing
+        let existing = ["a.jpeg", "b.jpeg", "b.jpg", "c.jpeg", "c.jpg"];
+        let existing = existing.iter().map(|name| name.to_string()).collect();
+
+        let conflicts: Vec<(String, bool)> = preview(names, &existing, &substitution)
+            .into_iter()
+            .map(|rename| (rename.new_name, rename.conflict))
+            .collect();
+        let expected = [
+            ("a.jpg", false),
+            ("b.jpg", true),
+            ("c.jpg", true),
+            ("c.jpg", false),
+        ];
+        let expected: Vec<(String, bool)> =
+            expected.iter().map(|&(name, conflict)| (name.to_string(), conflict)).collect();
+        assert_eq!(conflicts, expected);
+    }
+}
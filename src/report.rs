@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+use crate::{csv, OutputFormat, Settings};
+
+/// A row of a summary (e.g. one owner with --by-owner), serialized as it is to JSON and as
+/// text fields to CSV/TSV.
+pub trait Row: Serialize {
+    /// Names of the fields, heading CSV/TSV exports
+    const FIELDS: &'static [&'static str];
+
+    /// The fields as text, in the order of `FIELDS`.
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Prints the rows in the output format, tables being laid out by `print_table`, and returns
+/// the exit code.
+pub fn print<R: Row>(rows: &[R], settings: &Settings, print_table: impl FnOnce(&[R])) -> i32 {
+    match settings.output {
+        OutputFormat::Table => print_table(rows),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rows).unwrap()),
+        OutputFormat::Ndjson => {
+            for row in rows {
+                println!("{}", serde_json::to_string(row).unwrap());
+            }
+        }
+        OutputFormat::Csv => print_delimited(rows, ','),
+        OutputFormat::Tsv => print_delimited(rows, '\t'),
+    }
+    exit_code(settings, rows.is_empty())
+}
+
+/// Prints the rows as comma or tab separated values, after a header row.
+pub fn print_delimited<R: Row>(rows: &[R], separator: char) {
+    println!("{}", csv::record(R::FIELDS, separator));
+    for row in rows {
+        println!("{}", csv::record(&row.fields(), separator));
+    }
+}
+
+/// EXIT_EMPTY for an empty summary with --fail-if-empty, 0 otherwise.
+pub fn exit_code(settings: &Settings, is_empty: bool) -> i32 {
+    if settings.fail_if_empty && is_empty {
+        crate::EXIT_EMPTY
+    } else {
+        0
+    }
+}
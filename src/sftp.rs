@@ -0,0 +1,357 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone};
+use log::{debug, info};
+
+use crate::diagnostics::{self, EntryError};
+use crate::source::FileSource;
+use crate::{RawInfo, ScanOptions, FILE_TYPES};
+
+const SCHEME: &str = "sftp://";
+
+/// Whether a path given on the command line is a remote directory: sftp://[user@]host[:port]/path
+pub fn is_url(path: &str) -> bool {
+    path.starts_with(SCHEME)
+}
+
+/// A remote directory, as given on the command line.
+#[derive(Debug, PartialEq)]
+struct Location {
+    /// [user@]host, as passed to ssh
+    destination: String,
+    port: Option<String>,
+    /// Remote path. Without one, the user's home directory is listed.
+    path: String,
+}
+
+impl Location {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix(SCHEME)
+            .ok_or("expected sftp://[user@]host[:port]/path")?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "."),
+        };
+        let (destination, port) = match authority.rsplit_once(':') {
+            Some((destination, port)) => (destination, Some(port.to_string())),
+            None => (authority, None),
+        };
+        if destination.is_empty() || destination.ends_with('@') {
+            return Err("missing host".to_string());
+        }
+        // A newline would end the batch command and start another one (e.g. "!cmd")
+        if url.chars().any(char::is_control) {
+            return Err("control characters in URL".to_string());
+        }
+
+        Ok(Location {
+            destination: destination.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Remote directories read through the system's `sftp` client (OpenSSH), in batch mode, so the
+/// usual ssh config, keys and agent apply. Each directory is read once per run.
+///
+/// Entries get their URL as path. Remote owners are numeric, shown as the local users with
+/// the same ids, and what needs local access (directory sizes, git states) doesn't apply.
+#[derive(Default)]
+pub struct SftpSource {
+    listings: RefCell<HashMap<String, Result<Vec<RawInfo>, String>>>,
+}
+
+impl SftpSource {
+    // Entries of a remote directory, its own one (named ".") included
+    fn read(&self, url: &str) -> Result<Vec<RawInfo>, String> {
+        let url = normalize(url);
+        if let Some(listing) = self.listings.borrow().get(url) {
+            return listing.clone();
+        }
+
+        let listing = run_ls(url);
+        self.listings
+            .borrow_mut()
+            .insert(url.to_string(), listing.clone());
+        listing
+    }
+
+    // Finds an entry without reporting errors: a directory through its own listing,
+    // anything else through its parent's
+    fn lookup(&self, path: &Path) -> Result<Option<RawInfo>, String> {
+        let url = normalize(path.to_str().ok_or("invalid URL")?);
+        let own_entry = self.read(url).ok().and_then(|entries| {
+            entries
+                .into_iter()
+                .find(|raw_info| raw_info.path.as_os_str() == url)
+        });
+        if own_entry.is_some() {
+            return Ok(own_entry);
+        }
+
+        let parent = match url.rsplit_once('/') {
+            Some((parent, name)) if !name.is_empty() && parent.len() >= SCHEME.len() => {
+                // The root keeps its slash, "sftp://host" being the home directory
+                normalize(&url[..parent.len() + 1])
+            }
+            _ => return Ok(None),
+        };
+        Ok(self
+            .read(parent)?
+            .into_iter()
+            .find(|raw_info| raw_info.path.as_os_str() == url))
+    }
+}
+
+impl FileSource for SftpSource {
+    fn exists(&self, path: &Path) -> Result<bool, String> {
+        self.lookup(path).map(|raw_info| raw_info.is_some())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.lookup(path), Ok(Some(raw_info)) if raw_info.is_directory)
+    }
+
+    fn file_info(&self, path: &Path) -> Option<RawInfo> {
+        match self.lookup(path) {
+            Ok(Some(raw_info)) => Some(raw_info),
+            Ok(None) => {
+                let error = io::Error::from(io::ErrorKind::NotFound);
+                diagnostics::report(EntryError::new(path, "Error accessing", &error));
+                None
+            }
+            Err(e) => {
+                let error = io::Error::other(e);
+                diagnostics::report(EntryError::new(path, "Error accessing", &error));
+                None
+            }
+        }
+    }
+
+    fn list_directory(&self, directory: &Path, scan: &ScanOptions) -> Vec<RawInfo> {
+        let url = directory.to_string_lossy();
+        let entries = match self.read(&url) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let error = io::Error::other(e);
+                diagnostics::report(EntryError::new(directory, "Cannot read directory", &error));
+                return Vec::new();
+            }
+        };
+
+        let own_url = normalize(&url);
+        entries
+            .into_iter()
+            .filter(|raw_info| raw_info.path.as_os_str() != own_url)
+            .filter(|raw_info| {
+                let name = raw_info
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                if !scan.show_hidden && name.starts_with('.') {
+                    debug!("Skipped {}: hidden", raw_info.path.display());
+                    return false;
+                }
+                scan.accepts(&raw_info.path, &name)
+            })
+            .collect()
+    }
+}
+
+// Drops trailing slashes, except the one of the root directory ("sftp://host/").
+fn normalize(url: &str) -> &str {
+    let trimmed = url.trim_end_matches('/');
+    match trimmed.strip_prefix(SCHEME) {
+        Some(rest) if !rest.contains('/') && trimmed.len() < url.len() => &url[..trimmed.len() + 1],
+        _ => trimmed,
+    }
+}
+
+// Runs `ls` on a remote directory, giving each entry its URL as path.
+fn run_ls(url: &str) -> Result<Vec<RawInfo>, String> {
+    let location = Location::parse(url)?;
+
+    let mut command = Command::new("sftp");
+    command.args(["-q", "-b", "-"]);
+    if let Some(port) = &location.port {
+        command.args(["-P", port]);
+    }
+    // Hosts starting with "-" aren't options
+    let mut child = command
+        .arg("--")
+        .arg(&location.destination)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run sftp: {}", e))?;
+
+    // -a for the directory's own entry, -n for numeric owners
+    let quoted_path = location.path.replace('\\', "\\\\").replace('"', "\\\"");
+    let batch = format!("ls -lan \"{}\"\n", quoted_path);
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(batch.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let mut raw_infos = Vec::new();
+    for line in listing.lines() {
+        let Some((name, mut raw_info)) = parse_line(line) else {
+            continue;
+        };
+        match name {
+            "." => raw_info.path = PathBuf::from(url),
+            ".." => continue,
+            _ => {
+                let child = format!("{}/{}", url.trim_end_matches('/'), name);
+                raw_info.path = PathBuf::from(child);
+            }
+        }
+        raw_infos.push(raw_info);
+    }
+
+    info!("Listed {} remote entries from {}", raw_infos.len(), url);
+    Ok(raw_infos)
+}
+
+// Reads an entry from sftp's long listing, e.g.
+// "-rw-r--r--    1 1000     1000         4096 Mar  5 14:02 /srv/data/notes.txt"
+fn parse_line(line: &str) -> Option<(&str, RawInfo)> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+    for _ in 0..8 {
+        rest = rest.trim_start();
+        let end = rest.find(' ')?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    let path = rest.strip_prefix(' ')?;
+    let name = path.rsplit('/').next().filter(|name| !name.is_empty())?;
+
+    let mode = fields[0].as_bytes();
+    if mode.len() < 10 {
+        return None;
+    }
+    let permissions: u32 = mode[1..10]
+        .iter()
+        .zip([
+            0o400, 0o200, 0o100, 0o040, 0o020, 0o010, 0o004, 0o002, 0o001,
+        ])
+        .filter(|&(&c, _)| c != b'-' && c != b'S' && c != b'T')
+        .map(|(_, bit)| bit)
+        .sum();
+
+    let file_type = FILE_TYPES
+        .iter()
+        .find(|&&(_, letter)| letter == mode[0] as char)
+        .map_or(0, |&(bits, _)| bits);
+    let special: u32 = [(3, 0o4000), (6, 0o2000), (9, 0o1000)]
+        .iter()
+        .filter(|&&(i, _)| matches!(mode[i], b's' | b'S' | b't' | b'T'))
+        .map(|(_, bit)| bit)
+        .sum();
+
+    let mut raw_info = RawInfo::empty(Path::new(path));
+    raw_info.mode = file_type | special | permissions;
+    raw_info.links = fields[1].parse().ok()?;
+    raw_info.is_directory = mode[0] == b'd';
+    raw_info.is_symlink = mode[0] == b'l';
+    raw_info.is_executable = permissions & 0o100 != 0;
+    raw_info.owner_uid = fields[2].parse().ok()?;
+    raw_info.group_gid = fields[3].parse().ok()?;
+    raw_info.size = fields[4].parse().ok()?;
+    raw_info.modified_time = parse_date(fields[5], fields[6], fields[7], Local::now())?;
+    Some((name, raw_info))
+}
+
+// Dates are "Mar  5  2023" or, for the last six months, "Mar  5 14:02" without the year.
+fn parse_date(
+    month: &str,
+    day: &str,
+    time_or_year: &str,
+    now: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let (year, time) = match NaiveTime::parse_from_str(time_or_year, "%H:%M") {
+        Ok(time) => (now.year(), time),
+        Err(_) => (time_or_year.parse().ok()?, NaiveTime::MIN),
+    };
+    let date =
+        NaiveDate::parse_from_str(&format!("{} {} {}", year, month, day), "%Y %b %d").ok()?;
+    let date_time = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+
+    // Without a year, a date after today is from last year
+    if date_time > now + chrono::Duration::days(1) {
+        let date = date.with_year(year - 1)?;
+        return Local.from_local_datetime(&date.and_time(time)).earliest();
+    }
+    Some(date_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let location = Location::parse("sftp://me@example.com:2222/srv/data").unwrap();
+        assert_eq!(location.destination, "me@example.com");
+        assert_eq!(location.port.as_deref(), Some("2222"));
+        assert_eq!(location.path, "/srv/data");
+
+        assert_eq!(Location::parse("sftp://example.com").unwrap().path, ".");
+        assert!(Location::parse("sftp:///srv").is_err());
+        assert!(Location::parse("sftp://host/srv\n!touch /tmp/x").is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("sftp://host/srv/"), "sftp://host/srv");
+        assert_eq!(normalize("sftp://host//"), "sftp://host/");
+        assert_eq!(normalize("sftp://host"), "sftp://host");
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let line = "-rwxr-x---    1 1000     100          4096 Mar  5  2023 /srv/my notes.sh";
+        let (name, raw_info) = parse_line(line).unwrap();
+        assert_eq!(name, "my notes.sh");
+        assert_eq!(raw_info.permissions(), 0o750);
+        assert_eq!(raw_info.mode, 0o100750);
+        assert_eq!(raw_info.links, 1);
+        assert_eq!(
+            (raw_info.owner_uid, raw_info.group_gid, raw_info.size),
+            (1000, 100, 4096)
+        );
+        assert!(raw_info.is_executable && !raw_info.is_directory);
+        assert_eq!(
+            raw_info.modified_time.format("%Y-%m-%d").to_string(),
+            "2023-03-05"
+        );
+
+        assert!(parse_line("sftp> ls -lan \"/srv\"").is_none());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let now = Local.with_ymd_and_hms(2024, 2, 10, 12, 0, 0).unwrap();
+        let date = |time_or_year| parse_date("Mar", "5", time_or_year, now).unwrap();
+        assert_eq!(
+            date("14:02").format("%Y-%m-%d %H:%M").to_string(),
+            "2023-03-05 14:02"
+        );
+        assert_eq!(date("2021").format("%Y-%m-%d").to_string(), "2021-03-05");
+    }
+}
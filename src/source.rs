@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::{get_file_info, list_directory, sftp, RawInfo, ScanOptions};
+
+/// Where listed entries are read from. Everything after the scan (filters, sorting,
+/// formatting) only sees the `RawInfo` a source produces.
+pub trait FileSource {
+    /// Whether there's an entry at the path, if that can be told.
+    fn exists(&self, path: &Path) -> Result<bool, String>;
+
+    /// Whether the path is a directory, or a link to one.
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Info about the entry itself, links not being followed. Errors are reported.
+    fn file_info(&self, path: &Path) -> Option<RawInfo>;
+
+    /// Entries inside a directory that pass the scan options. Errors are reported.
+    fn list_directory(&self, directory: &Path, scan: &ScanOptions) -> Vec<RawInfo>;
+}
+
+/// The local filesystem.
+pub struct LocalFs;
+
+impl FileSource for LocalFs {
+    fn exists(&self, path: &Path) -> Result<bool, String> {
+        Ok(path.exists())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn file_info(&self, path: &Path) -> Option<RawInfo> {
+        get_file_info(path)
+    }
+
+    fn list_directory(&self, directory: &Path, scan: &ScanOptions) -> Vec<RawInfo> {
+        list_directory(directory, scan)
+    }
+}
+
+/// The source able to read the paths given on the command line: remote if they're sftp:// URLs.
+/// Local paths and URLs can't be mixed, a listing having a single source.
+pub fn for_paths(paths: &[String]) -> Result<Box<dyn FileSource>, String> {
+    let remote = paths.iter().filter(|path| sftp::is_url(path)).count();
+    if remote == 0 {
+        Ok(Box::new(LocalFs))
+    } else if remote == paths.len() {
+        Ok(Box::new(sftp::SftpSource::default()))
+    } else {
+        Err("sftp:// URLs can't be listed along with local paths".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_paths() {
+        let source = |paths: &[&str]| {
+            let paths: Vec<String> = paths.iter().map(|path| path.to_string()).collect();
+            for_paths(&paths)
+        };
+        assert!(source(&["a.rs", "src"]).is_ok());
+        assert!(source(&["sftp://host/a", "sftp://host/b"]).is_ok());
+        assert!(source(&["a.rs", "sftp://host/x"]).is_err());
+    }
+}
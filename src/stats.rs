@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::report::{self, Row};
+use crate::{ProcessedInfo, RawInfo, Settings};
+
+/// Extension standing for files without one.
+const NO_EXTENSION: &str = "(none)";
+
+/// Number and combined size of the files with one extension, and the largest of them.
+#[derive(Debug, PartialEq, Serialize)]
+struct ExtensionUsage {
+    extension: String,
+    files: u64,
+    size: u64,
+    largest: String,
+    largest_size: u64,
+}
+
+/// Prints one row per file extension (case ignored), those taking the most space first.
+/// Directories are left out, only the files inside them are counted.
+pub fn print(raw_infos: &[RawInfo], settings: &Settings) -> i32 {
+    let usages = aggregate(raw_infos);
+    report::print(&usages, settings, |usages| print_table(usages, settings))
+}
+
+impl Row for ExtensionUsage {
+    const FIELDS: &'static [&'static str] =
+        &["extension", "files", "size", "largest", "largest_size"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.extension.clone(),
+            self.files.to_string(),
+            self.size.to_string(),
+            self.largest.clone(),
+            self.largest_size.to_string(),
+        ]
+    }
+}
+
+fn aggregate(raw_infos: &[RawInfo]) -> Vec<ExtensionUsage> {
+    let mut by_extension: HashMap<String, ExtensionUsage> = HashMap::new();
+    for raw_info in raw_infos.iter().filter(|r| !r.is_directory && !r.vanished) {
+        let extension = raw_info
+            .path
+            .extension()
+            .map_or(NO_EXTENSION.to_string(), |e| {
+                e.to_string_lossy().to_lowercase()
+            });
+        let usage = by_extension
+            .entry(extension.clone())
+            .or_insert_with(|| ExtensionUsage {
+                extension,
+                files: 0,
+                size: 0,
+                largest: String::new(),
+                largest_size: 0,
+            });
+        usage.files += 1;
+        usage.size += raw_info.size;
+        if usage.largest.is_empty() || raw_info.size > usage.largest_size {
+            usage.largest = raw_info.label.clone().unwrap_or_else(|| {
+                let name = raw_info.path.file_name().unwrap_or_default();
+                name.to_string_lossy().to_string()
+            });
+            usage.largest_size = raw_info.size;
+        }
+    }
+
+    let mut usages: Vec<ExtensionUsage> = by_extension.into_values().collect();
+    usages.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    usages
+}
+
+fn print_table(usages: &[ExtensionUsage], settings: &Settings) {
+    let extension_colsize = usages
+        .iter()
+        .map(|u| u.extension.len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    let header = format!(
+        "{:<width$}   FILES    SIZE LARGEST",
+        "EXT",
+        width = extension_colsize
+    );
+    println!("{}", settings.header(&header));
+    for usage in usages {
+        let (size, unit) = ProcessedInfo::fmt_size_and_unit(usage.size);
+        let (largest_size, largest_unit) = ProcessedInfo::fmt_size_and_unit(usage.largest_size);
+        println!(
+            "{:<width$} {:>7} {:>6}{} {} ({}{})",
+            usage.extension,
+            usage.files,
+            size,
+            unit,
+            usage.largest,
+            largest_size,
+            largest_unit,
+            width = extension_colsize
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let raw_info = crate::tests::mock_raw_info;
+        let usages = aggregate(&[
+            raw_info("/tmp/a.log", 10, false),
+            raw_info("/tmp/movie.MKV", 700, false),
+            raw_info("/tmp/b.log", 30, false),
+            raw_info("/tmp/other.mkv", 100, false),
+            raw_info("/tmp/Makefile", 5, false),
+            raw_info("/tmp/dir.d", 4096, true),
+        ]);
+
+        let rows: Vec<(&str, u64, u64, &str)> = usages
+            .iter()
+            .map(|u| (u.extension.as_str(), u.files, u.size, u.largest.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("mkv", 2, 800, "movie.MKV"),
+                ("log", 2, 40, "b.log"),
+                (NO_EXTENSION, 1, 5, "Makefile")
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::env;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use log::debug;
+
+/// Who may read a file written by myls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// As any file the user creates: 0666 minus the umask. A replaced file keeps its mode.
+    Default,
+    /// Only the user (0600, in a 0700 directory), for data embedding path names (e.g. caches)
+    Private,
+}
+
+/// Directory holding myls' configuration files: `$XDG_CONFIG_HOME/myls` or `~/.config/myls`.
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Directory holding myls' cached data: `$XDG_CACHE_HOME/myls` or `~/.cache/myls`.
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+fn xdg_dir(variable: &str, home_fallback: &str) -> Option<PathBuf> {
+    let base = match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(home_fallback),
+    };
+    Some(base.join("myls"))
+}
+
+/// Writes a file through a temporary one renamed over it, so readers (and a crash halfway)
+/// never leave it truncated. Missing parent directories are created.
+pub fn write(path: &Path, contents: &[u8], visibility: Visibility) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let dir_mode = match visibility {
+        Visibility::Default => 0o777,
+        Visibility::Private => 0o700,
+    };
+    DirBuilder::new()
+        .recursive(true)
+        .mode(dir_mode)
+        .create(dir)
+        .map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+
+    let name = path.file_name().ok_or("missing file name")?;
+    let temp_path = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+    let written = write_new(&temp_path, contents, visibility, path)
+        .and_then(|()| fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("could not write {}: {}", path.display(), e));
+    }
+
+    debug!("Wrote {}", path.display());
+    Ok(())
+}
+
+// Creates the temporary file, with the mode of the one it replaces unless it must be private
+fn write_new(
+    temp_path: &Path,
+    contents: &[u8],
+    visibility: Visibility,
+    replaced: &Path,
+) -> std::io::Result<()> {
+    let mode = match visibility {
+        Visibility::Default => 0o666,
+        Visibility::Private => 0o600,
+    };
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(temp_path)?;
+    if visibility == Visibility::Private {
+        // The umask can only remove bits, but the file may come from an earlier, looser version
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    } else if let Ok(metadata) = fs::metadata(replaced) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let dir = crate::tests::TempDir::new("storage");
+        let private = dir.join("cache/sizes.json");
+        write(&private, b"{}", Visibility::Private).unwrap();
+        write(&private, b"{\"a\": 1}", Visibility::Private).unwrap();
+        assert_eq!(fs::read(&private).unwrap(), b"{\"a\": 1}");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&private), 0o600);
+        assert_eq!(mode(&dir.join("cache")), 0o700);
+
+        // Replaced files keep the mode chosen by the user
+        let config = dir.join("config.toml");
+        write(&config, b"icons = true", Visibility::Default).unwrap();
+        fs::set_permissions(&config, fs::Permissions::from_mode(0o640)).unwrap();
+        write(&config, b"icons = false", Visibility::Default).unwrap();
+        assert_eq!(mode(&config), 0o640);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+}
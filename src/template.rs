@@ -0,0 +1,201 @@
+use clap::ValueEnum;
+
+use crate::{visible_width, Column};
+
+/// Layout of a row given with --format, e.g. "{perm} {size:>8} {name}". Placeholders are
+/// named after the columns and may be padded to a width as in Rust (`<` left, `>` right,
+/// `^` centered). Braces are written `{{` and `}}`.
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field {
+        column: Column,
+        align: Align,
+        width: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err("unclosed { (write {{ for a brace)".to_string()),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(parse_field(&placeholder)?);
+                }
+                '}' => return Err("unmatched } (write }} for a brace)".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Columns the template shows.
+    pub fn columns(&self) -> impl Iterator<Item = Column> + '_ {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Field { column, .. } => Some(*column),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// Fills the placeholders with the cells of a row, without their table padding.
+    pub fn render<'a>(&self, cell: impl Fn(Column) -> &'a str) -> String {
+        let mut row = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => row.push_str(text),
+                Part::Field {
+                    column,
+                    align,
+                    width,
+                } => {
+                    let value = trim(cell(*column));
+                    let padding = width.saturating_sub(visible_width(&value));
+                    let (before, after) = match align {
+                        Align::Left => (0, padding),
+                        Align::Right => (padding, 0),
+                        Align::Center => (padding / 2, padding - padding / 2),
+                    };
+                    row.push_str(&" ".repeat(before));
+                    row.push_str(&value);
+                    row.push_str(&" ".repeat(after));
+                }
+            }
+        }
+        row
+    }
+}
+
+// A placeholder without its braces, e.g. "size:>8".
+fn parse_field(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let column = Column::from_str(name.trim(), true).map_err(|_| {
+        let names: Vec<String> = Column::value_variants()
+            .iter()
+            .filter_map(|c| c.to_possible_value().map(|v| v.get_name().to_string()))
+            .collect();
+        format!(
+            "unknown field {{{}}}, expected one of {}",
+            name,
+            names.join(", ")
+        )
+    })?;
+    let (align, width) = match spec.chars().next() {
+        Some('<') => (Align::Left, &spec[1..]),
+        Some('>') => (Align::Right, &spec[1..]),
+        Some('^') => (Align::Center, &spec[1..]),
+        _ => (Align::Left, spec),
+    };
+    let width = if width.is_empty() {
+        0
+    } else {
+        width
+            .parse()
+            .map_err(|_| format!("invalid width in {{{}}}", placeholder))?
+    };
+    Ok(Part::Field {
+        column,
+        align,
+        width,
+    })
+}
+
+// Removes the spaces around the visible text of a cell, keeping its escape sequences.
+fn trim(cell: &str) -> String {
+    let mut tokens: Vec<(&str, bool)> = Vec::new();
+    let mut rest = cell;
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '\x1b' {
+            rest.find(|c: char| c.is_ascii_alphabetic())
+                .map_or(rest.len(), |end| end + 1)
+        } else {
+            c.len_utf8()
+        };
+        tokens.push((&rest[..len], c == ' '));
+        rest = &rest[len..];
+    }
+    let visible = |&(token, space): &(&str, bool)| !space && !token.starts_with('\x1b');
+    let start = tokens.iter().position(visible).unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(visible)
+        .map_or(start, |end| end + 1);
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|&(i, &(_, space))| (start..end).contains(&i) || !space)
+        .map(|(_, (token, _))| *token)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let template = Template::parse("{perm} {size:>8} {{{name}}}").unwrap();
+        let cells = |column| match column {
+            Column::Perm => " 755",
+            Column::Size => "\x1b[32m   1.5M\x1b[0m",
+            _ => "file ",
+        };
+        assert_eq!(template.render(cells), "755     \x1b[32m1.5M\x1b[0m {file}");
+        let columns: Vec<Column> = template.columns().collect();
+        assert_eq!(columns, vec![Column::Perm, Column::Size, Column::Name]);
+
+        let centered = Template::parse("[{owner:^6}]").unwrap();
+        assert_eq!(centered.render(|_| "bob"), "[ bob  ]");
+
+        assert!(Template::parse("{nope}").is_err());
+        assert!(Template::parse("{size:>x}").is_err());
+        assert!(Template::parse("size}").is_err());
+        assert!(Template::parse("{size").is_err());
+    }
+
+    #[test]
+    fn test_trim() {
+        assert_eq!(
+            trim("\x1b[0m\x1b[48;5;236m 755"),
+            "\x1b[0m\x1b[48;5;236m755"
+        );
+        assert_eq!(trim("  12:30 \x1b[0m"), "12:30\x1b[0m");
+        assert_eq!(trim("   "), "");
+    }
+}
@@ -0,0 +1,184 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Built-in color schemes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Red-green color blindness (green weak): blue and orange instead of green and red
+    Deuteranopia,
+    /// Red-green color blindness (red weak): blue and yellow, as reds look dark
+    Protanopia,
+    /// Blue-yellow color blindness: cyan and red/pink instead of blues and yellows
+    Tritanopia,
+}
+
+/// What each kind of information in a table is painted with, as ANSI escape sequences.
+/// Color-blind themes also underline what needs attention, so it doesn't rely on hue alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub executable: &'static str,
+    /// Unit of sizes under a megabyte
+    pub size_small: &'static str,
+    pub size_mega: &'static str,
+    pub size_giga: &'static str,
+    /// Modification dates: today, this month, older, and in the future
+    pub date_today: &'static str,
+    pub date_recent: &'static str,
+    pub date_old: &'static str,
+    pub date_future: &'static str,
+    /// Negative days left in the expires column
+    pub expired: &'static str,
+    /// Parts of names matched by a filter
+    pub matched: &'static str,
+    /// Groups the user belongs to, and the others
+    pub own_group: &'static str,
+    pub other_group: &'static str,
+    /// Permissions of the owner, the group and the others
+    pub perm_user: &'static str,
+    pub perm_group: &'static str,
+    pub perm_other: &'static str,
+    /// Permissions of the others when they can write
+    pub world_writable: &'static str,
+    /// Permissions of the group when it can write, with `--writable-warning group`
+    pub group_writable: &'static str,
+    /// Special and owner permissions of setuid executables
+    pub setuid: &'static str,
+    /// Entries added, removed or changed by the diff and rename-preview commands, and renames
+    /// overwriting another entry
+    pub added: &'static str,
+    pub removed: &'static str,
+    pub changed: &'static str,
+    pub conflict: &'static str,
+}
+
+impl Theme {
+    pub const DEFAULT: Theme = Theme {
+        executable: "\x1b[32m",
+        size_small: "\x1b[32m",
+        size_mega: "\x1b[33m",
+        size_giga: "\x1b[31m",
+        date_today: "\x1b[37m",
+        date_recent: "\x1b[38;5;39m",
+        date_old: "\x1b[38;5;33m",
+        date_future: "\x1b[38;5;196m",
+        expired: "\x1b[31m",
+        matched: "\x1b[1;38;5;208m",
+        own_group: "\x1b[1m",
+        other_group: "\x1b[2m",
+        perm_user: "\x1b[33m",
+        perm_group: "\x1b[36m",
+        perm_other: "\x1b[35m",
+        world_writable: "\x1b[1;31m",
+        group_writable: "\x1b[1;33m",
+        setuid: "\x1b[1;38;5;196m",
+        added: "\x1b[32m",
+        removed: "\x1b[31m",
+        changed: "\x1b[33m",
+        conflict: "\x1b[33m",
+    };
+
+    const DEUTERANOPIA: Theme = Theme {
+        executable: "\x1b[1;38;5;75m",
+        size_small: "\x1b[38;5;75m",
+        size_mega: "\x1b[38;5;220m",
+        size_giga: "\x1b[1;4;38;5;208m",
+        date_future: "\x1b[1;4;38;5;208m",
+        expired: "\x1b[1;4;38;5;208m",
+        matched: "\x1b[1;4;38;5;214m",
+        world_writable: "\x1b[1;4;38;5;208m",
+        group_writable: "\x1b[1;4;38;5;220m",
+        setuid: "\x1b[1;4;38;5;208m",
+        added: "\x1b[1;38;5;75m",
+        removed: "\x1b[4;38;5;208m",
+        changed: "\x1b[38;5;220m",
+        conflict: "\x1b[1;4;38;5;208m",
+        ..Theme::DEFAULT
+    };
+
+    const PROTANOPIA: Theme = Theme {
+        executable: "\x1b[1;38;5;75m",
+        size_small: "\x1b[38;5;75m",
+        size_mega: "\x1b[38;5;229m",
+        size_giga: "\x1b[1;4;38;5;226m",
+        date_future: "\x1b[1;4;38;5;226m",
+        expired: "\x1b[1;4;38;5;226m",
+        matched: "\x1b[1;4;38;5;229m",
+        world_writable: "\x1b[1;4;38;5;226m",
+        group_writable: "\x1b[1;4;38;5;229m",
+        setuid: "\x1b[1;4;38;5;226m",
+        added: "\x1b[1;38;5;75m",
+        removed: "\x1b[4;38;5;226m",
+        changed: "\x1b[38;5;229m",
+        conflict: "\x1b[1;4;38;5;226m",
+        ..Theme::DEFAULT
+    };
+
+    const TRITANOPIA: Theme = Theme {
+        executable: "\x1b[1;38;5;44m",
+        size_small: "\x1b[38;5;44m",
+        size_mega: "\x1b[38;5;211m",
+        size_giga: "\x1b[1;4;38;5;196m",
+        date_recent: "\x1b[38;5;44m",
+        date_old: "\x1b[38;5;175m",
+        date_future: "\x1b[1;4;38;5;196m",
+        expired: "\x1b[1;4;38;5;196m",
+        matched: "\x1b[1;4;38;5;203m",
+        perm_group: "\x1b[38;5;44m",
+        perm_other: "\x1b[38;5;175m",
+        world_writable: "\x1b[1;4;38;5;196m",
+        group_writable: "\x1b[1;4;38;5;211m",
+        setuid: "\x1b[1;4;38;5;196m",
+        added: "\x1b[1;38;5;44m",
+        removed: "\x1b[4;38;5;196m",
+        changed: "\x1b[38;5;211m",
+        conflict: "\x1b[1;4;38;5;196m",
+        ..Theme::DEFAULT
+    };
+
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Default => Theme::DEFAULT,
+            ThemeName::Deuteranopia => Theme::DEUTERANOPIA,
+            ThemeName::Protanopia => Theme::PROTANOPIA,
+            ThemeName::Tritanopia => Theme::TRITANOPIA,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_blind_themes() {
+        for name in [ThemeName::Deuteranopia, ThemeName::Protanopia, ThemeName::Tritanopia] {
+            let theme = Theme::named(name);
+            // Warnings are underlined, executables told apart from small sizes by weight, and
+            // additions from removals by weight and underline
+            let warnings = [
+                theme.size_giga,
+                theme.date_future,
+                theme.expired,
+                theme.world_writable,
+                theme.group_writable,
+                theme.setuid,
+                theme.conflict,
+            ];
+            for warning in warnings {
+                assert!(warning.starts_with("\x1b[1;4;"), "{:?}", name);
+            }
+            assert_ne!(theme.executable, theme.size_small);
+            assert!(theme.added.starts_with("\x1b[1;"), "{:?}", name);
+            assert!(theme.removed.starts_with("\x1b[4;"), "{:?}", name);
+        }
+    }
+}
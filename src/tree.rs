@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use crate::{get_file_info, list_directory, print_listing, DisplayArgs, ProcessedInfo, Settings};
+
+#[derive(clap::Args)]
+pub struct TreeArgs {
+    /// Directory to show (default: current directory)
+    #[arg(default_value = ".")]
+    path: String,
+
+    /// Maximum depth to descend. If 0 (default), no limit is applied.
+    #[arg(short, long, default_value = "0")]
+    depth: usize,
+
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+pub fn run(args: &TreeArgs, settings: &Settings) -> i32 {
+    let path = crate::resolve_paths(std::slice::from_ref(&args.path)).remove(0);
+    if !path.is_dir() {
+        eprintln!("Error: {} is not a directory", path.display());
+        return 1;
+    }
+
+    let mut rows = Vec::new();
+    if let Some(mut root_info) = get_file_info(&path) {
+        root_info.is_main_dir = true;
+        rows.push(settings.process(root_info));
+    }
+    walk(&path, "", 1, args, settings, &mut rows);
+
+    print_listing(rows, settings)
+}
+
+// Appends the sorted contents of a directory, each subdirectory followed by its own contents.
+fn walk(
+    directory: &Path,
+    prefix: &str,
+    depth: usize,
+    args: &TreeArgs,
+    settings: &Settings,
+    rows: &mut Vec<ProcessedInfo>,
+) {
+    // Directories are kept regardless of filters, to preserve the tree structure
+    let children: Vec<ProcessedInfo> = settings
+        .process_sorted(list_directory(directory, &settings.scan))
+        .into_iter()
+        .filter(|child| child.rinfo.is_directory || settings.keep(&child.rinfo))
+        .collect();
+    let count = children.len();
+
+    for (i, mut child) in children.into_iter().enumerate() {
+        let is_last = i + 1 == count;
+        let (connector, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        // Symlinks are never followed, to avoid cycles
+        let subdir: Option<PathBuf> = if child.rinfo.is_directory
+            && !child.rinfo.is_symlink
+            && (args.depth == 0 || depth < args.depth)
+        {
+            Some(child.rinfo.path.clone())
+        } else {
+            None
+        };
+
+        child.name = format!("{}{}{}", prefix, connector, child.name);
+        rows.push(child);
+
+        if let Some(subdir) = subdir {
+            walk(&subdir, &format!("{}{}", prefix, indent), depth + 1, args, settings, rows);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, CommandFactory};
+use log::debug;
+use toml::{Table, Value};
+
+use crate::Args;
+
+/// Replaces `--view NAME` on the command line with the options of the view defined in the
+/// `[view.NAME]` table of the config file (e.g. `columns = ["size", "name"]`, `type = "image"`,
+/// `sort = "size"`). Keys are long option names, with `_` or `-`. Options also given on the
+/// command line win over the view's. Returns None when no view is asked for.
+pub fn expand(
+    argv: Vec<OsString>,
+    views: &HashMap<String, Table>,
+) -> Result<Option<Vec<OsString>>, String> {
+    let command = Args::command();
+    let matches = match command.clone().try_get_matches_from(&argv) {
+        Ok(matches) => matches,
+        Err(_) => return Ok(None),
+    };
+
+    // The view belongs to the (sub)command whose options it holds
+    let (mut command, mut matches) = (&command, &matches);
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        command = match command.find_subcommand(name) {
+            Some(subcommand) => subcommand,
+            None => return Ok(None),
+        };
+        matches = sub_matches;
+    }
+    let name = match matches.try_get_one::<String>("view") {
+        Ok(Some(name)) => name,
+        _ => return Ok(None),
+    };
+    let view = views.get(name).ok_or_else(|| {
+        let mut names: Vec<&str> = views.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        if names.is_empty() {
+            format!("unknown view {}: the config file defines none", name)
+        } else {
+            format!(
+                "unknown view {}, expected one of {}",
+                name,
+                names.join(", ")
+            )
+        }
+    })?;
+
+    let mut options = Vec::new();
+    for (key, value) in view {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()) && long != "view")
+            .ok_or_else(|| format!("unknown option {} in view {}", key, name))?;
+        if given(matches, arg) {
+            debug!("--{} of view {} overridden by the command line", long, name);
+            continue;
+        }
+        options.extend(
+            option(arg, &long, value)
+                .map_err(|e| format!("invalid {} in view {}: {}", key, name, e))?,
+        );
+    }
+
+    let mut argv = argv;
+    let is_view = |arg: &OsString| arg.to_string_lossy().starts_with("--view=");
+    if let Some(position) = argv.iter().position(|arg| arg == "--view") {
+        argv.splice(position..position + 2, options);
+    } else if let Some(position) = argv.iter().position(is_view) {
+        argv.splice(position..=position, options);
+    }
+    Ok(Some(argv))
+}
+
+fn given(matches: &ArgMatches, arg: &Arg) -> bool {
+    matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+}
+
+// Command line words for an option of a view.
+fn option(arg: &Arg, long: &str, value: &Value) -> Result<Vec<OsString>, String> {
+    if !arg.get_action().takes_values() {
+        return match value {
+            Value::Boolean(true) => Ok(vec![format!("--{}", long).into()]),
+            Value::Boolean(false) => Ok(Vec::new()),
+            _ => Err("expected true or false".to_string()),
+        };
+    }
+    let values = match value {
+        Value::Array(values) => values.iter().map(scalar).collect::<Result<Vec<_>, _>>()?,
+        value => vec![scalar(value)?],
+    };
+    // Lists are joined for options taking several values at once, repeated otherwise
+    let values = match arg.get_value_delimiter() {
+        Some(delimiter) => vec![values.join(&delimiter.to_string())],
+        None => values,
+    };
+    Ok(values
+        .into_iter()
+        .map(|value| format!("--{}={}", long, value).into())
+        .collect())
+}
+
+fn scalar(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => Ok(value.to_string()),
+        _ => Err(format!("unexpected value {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let views: HashMap<String, Table> = toml::from_str(
+            r#"
+            media = { columns = ["size", "name"], type = "image,video", sort = "size",
+                      reverse = true, match = ["*.jpg", "*.png"], max_name_length = 20 }
+            "#,
+        )
+        .unwrap();
+        let expand = |argv: &[&str]| {
+            let argv = argv.iter().map(OsString::from).collect();
+            expand(argv, &views).map(|argv| {
+                argv.map(|argv| {
+                    argv.iter()
+                        .map(|a| a.to_string_lossy().to_string())
+                        .collect()
+                })
+            })
+        };
+
+        let expanded: Option<Vec<String>> =
+            expand(&["myls", "--view", "media", "-r", "d"]).unwrap();
+        assert_eq!(
+            expanded.unwrap(),
+            [
+                "myls",
+                "--columns=size,name",
+                "--match=*.jpg",
+                "--match=*.png",
+                "--max-name-length=20",
+                "--sort=size",
+                "--type=image,video",
+                "-r",
+                "d"
+            ]
+        );
+        let expanded = expand(&["myls", "tree", "--view=media", "--sort", "name"]).unwrap();
+        assert_eq!(expanded.unwrap()[2], "--columns=size,name");
+
+        assert_eq!(expand(&["myls", "-r"]).unwrap(), None);
+        assert!(expand(&["myls", "--view", "music"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_view() {
+        let views: HashMap<String, Table> =
+            toml::from_str("a = { colour = \"always\" }\nb = { reverse = \"yes\" }").unwrap();
+        let expand = |view: &str| {
+            expand(
+                vec!["myls".into(), format!("--view={}", view).into()],
+                &views,
+            )
+        };
+        assert!(expand("a").unwrap_err().contains("unknown option colour"));
+        assert!(expand("b").unwrap_err().contains("expected true or false"));
+    }
+}
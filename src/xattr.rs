@@ -0,0 +1,39 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Number of extended attributes of the entry itself (symlinks aren't followed),
+/// or None if they can't be listed (e.g. unsupported by the filesystem).
+pub fn count(path: &Path) -> Option<usize> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+    // A first call with an empty buffer gives the size of the name list
+    let size = list(&c_path, std::ptr::null_mut(), 0);
+    if size <= 0 {
+        return if size == 0 { Some(0) } else { None };
+    }
+
+    let mut names = vec![0u8; size as usize];
+    let size = list(&c_path, names.as_mut_ptr() as *mut libc::c_char, names.len());
+    if size < 0 {
+        return None;
+    }
+
+    // Names are separated (and ended) by NUL bytes
+    Some(names[..size as usize].iter().filter(|&&b| b == 0).count())
+}
+
+#[cfg(target_os = "linux")]
+fn list(path: &CString, buffer: *mut libc::c_char, size: usize) -> isize {
+    unsafe { libc::llistxattr(path.as_ptr(), buffer, size) }
+}
+
+#[cfg(target_os = "macos")]
+fn list(path: &CString, buffer: *mut libc::c_char, size: usize) -> isize {
+    unsafe { libc::listxattr(path.as_ptr(), buffer, size, libc::XATTR_NOFOLLOW) }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn list(_path: &CString, _buffer: *mut libc::c_char, _size: usize) -> isize {
+    -1
+}